@@ -0,0 +1,98 @@
+#!/usr/bin/env python3
+"""Make data/district_boundary.json from the 시군구(SIG) boundary of 국가공간정보포털.
+
+1. Download the latest 시군구 boundary shapefile(SIG.shp) from http://www.nsdi.go.kr
+   (국가중점데이터 > 행정구역시군구_경계).
+2. Convert it to WGS84 GeoJSON.
+   > ogr2ogr -f GeoJSON -t_srs EPSG:4326 --config SHAPE_ENCODING CP949 sig.json SIG.shp
+3. Run this script.
+   > python3 scripts/make_district_boundary.py sig.json
+
+Features are renamed to the `code` and `name` properties the server reads, coordinates are
+rounded to about 1m and every code of data/district_code.txt is checked to have a boundary.
+"""
+
+import argparse
+import json
+import sys
+
+
+CODE_PROPS = ['SIG_CD', 'sig_cd', 'code']
+NAME_PROPS = ['SIG_KOR_NM', 'sig_kor_nm', 'name']
+PRECISION = 5
+
+
+def find_prop(props, names):
+    for name in names:
+        if props.get(name) is not None:
+            return str(props[name]).strip()
+    return None
+
+
+def round_ring(ring):
+    points = []
+    for pt in ring:
+        pt = [round(pt[0], PRECISION), round(pt[1], PRECISION)]
+        if not points or points[-1] != pt:
+            points.append(pt)
+    return points
+
+
+def round_geometry(geometry):
+    if geometry['type'] == 'Polygon':
+        coords = [round_ring(ring) for ring in geometry['coordinates']]
+    elif geometry['type'] == 'MultiPolygon':
+        coords = [[round_ring(ring) for ring in poly] for poly in geometry['coordinates']]
+    else:
+        raise ValueError('Unsupported geometry type: {}'.format(geometry['type']))
+
+    return {'type': geometry['type'], 'coordinates': coords}
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument('input', help='WGS84 GeoJSON of the SIG boundary')
+    parser.add_argument('-o', '--output', default='data/district_boundary.json')
+    parser.add_argument('-c', '--codes', default='data/district_code.txt')
+    args = parser.parse_args()
+
+    with open(args.input, encoding='utf-8') as f:
+        source = json.load(f)
+
+    with open(args.codes, encoding='utf-8') as f:
+        codes = [code.strip() for code in f.read().split(',') if code.strip()]
+
+    features = []
+    for feature in source['features']:
+        props = feature.get('properties') or {}
+        code = find_prop(props, CODE_PROPS)
+        name = find_prop(props, NAME_PROPS)
+
+        if code is None or name is None or feature.get('geometry') is None:
+            print('Skip a feature without code, name or geometry', file=sys.stderr)
+            continue
+
+        features.append({
+            'type': 'Feature',
+            'properties': {'code': code, 'name': name},
+            'geometry': round_geometry(feature['geometry']),
+        })
+
+    features.sort(key=lambda f: f['properties']['code'])
+
+    found = {f['properties']['code'] for f in features}
+    missing = [code for code in codes if code not in found]
+
+    with open(args.output, 'w', encoding='utf-8') as f:
+        json.dump({'type': 'FeatureCollection', 'features': features}, f,
+            ensure_ascii=False, separators=(',', ':'))
+
+    print('{} districts written to {}'.format(len(features), args.output))
+
+    if missing:
+        print('No boundary for {} codes: {}'.format(len(missing), ','.join(missing)), file=sys.stderr)
+        sys.exit(1)
+
+
+if __name__ == '__main__':
+    main()
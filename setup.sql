@@ -30,4 +30,63 @@ create table user_shelters (
     longitude double precision not null,
     info text,
     evidence text
-);
\ No newline at end of file
+);
+create table forecast_history (
+	id serial not null primary key,
+	code text not null,
+	lvl real not null,
+	fetched_time timestamp not null
+);
+create index forecast_history_code_time on forecast_history (code, fetched_time);
+create table wind_observations (
+	id serial not null primary key,
+	stn_id text not null,
+	latitude double precision not null,
+	longitude double precision not null,
+	wind_x double precision not null,
+	wind_y double precision not null,
+	observed_time timestamp not null
+);
+create index wind_observations_time on wind_observations (observed_time);
+create table fire_events (
+	id text not null primary key,
+	status_code text not null,
+	latitude double precision not null,
+	longitude double precision not null,
+	address text not null,
+	district text,
+	reported_date text not null,
+	reported_time text not null,
+	extinguished_date text,
+	extinguished_time text,
+	damage_area double precision,
+	cause text,
+	first_seen timestamp not null,
+	last_seen timestamp not null,
+	active boolean not null
+);
+create index fire_events_last_seen on fire_events (last_seen);
+create table fire_event_changes (
+	id serial not null primary key,
+	event_id text not null,
+	kind text not null,
+	old_code text,
+	new_code text,
+	changed_time timestamp not null
+);
+create index fire_event_changes_time on fire_event_changes (changed_time);
+create table active_fire_hotspots (
+	id serial not null primary key,
+	latitude double precision not null,
+	longitude double precision not null,
+	brightness real not null,
+	radiative_power real not null,
+	confidence real not null,
+	instrument text not null,
+	satellite text not null,
+	daynight text,
+	district text,
+	detected_time timestamp not null,
+	unique (latitude, longitude, detected_time, satellite)
+);
+create index active_fire_hotspots_time on active_fire_hotspots (detected_time);
\ No newline at end of file
@@ -1,13 +1,22 @@
 use std::{
+    env,
     sync::RwLock,
-    time::Duration,
+    time::{UNIX_EPOCH, Duration, SystemTime},
 };
-use rocket::{
-    response::content::Json,
+use rocket::response::{
+    content::Json,
+    status::BadRequest,
 };
 use serde_json::json;
-use chrono::NaiveDateTime;
+use chrono::{TimeZone, Utc};
 
+use crate::db;
+use crate::util;
+use crate::district;
+use crate::event_sys::{self, Layer};
+use crate::fire_sys;
+use crate::firms_parser::{self, FirmsRecord, Instrument};
+use crate::fire_correlation::{self, Hotspot};
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
 
@@ -15,31 +24,83 @@ lazy_static! {
     static ref FIRE_DATA: RwLock<String> = {
         RwLock::new(String::new())
     };
+    static ref FIRE_RECORDS: RwLock<Vec<FirmsRecord>> = {
+        RwLock::new(Vec::new())
+    };
+    static ref FIRMS_CONFIG: FirmsConfig = {
+        FirmsConfig::from_env()
+    };
 }
 
+const DEFAULT_FIRMS_REGION: &'static str = "Russia_and_Asia";
+const DEFAULT_FIRMS_PERIOD: &'static str = "24h";
+const FIRMS_PERIODS: [&'static str; 3] = ["24h", "48h", "7d"];
+const DEFAULT_BBOX: [f64; 4] = [32.477024, 123.825178, 39.322145, 132.799568];
+const DEFAULT_MIN_CONFIDENCE: f32 = 70.0;
+const DEFAULT_HISTORY_DAYS: i64 = 7;
+const MAX_HISTORY_DAYS: i64 = 366;
 
-struct FireRecord {
-    latitude: f64,
-    longitude: f64,
-    brightness: f32,
-    radiative_power: f32,
-    time: i64,
+
+struct FirmsConfig {
+    region: String,
+    period: String,
+    bbox: [f64; 4], // min_lat, min_lon, max_lat, max_lon
+    min_confidence: f32,
+}
+
+impl FirmsConfig {
+    fn from_env() -> Self {
+        let period = env::var("FIRMS_PERIOD").ok()
+            .filter(|period| {
+                let valid = FIRMS_PERIODS.contains(&period.as_str());
+                if !valid {
+                    warn!("Unknown FIRMS period: {}", period);
+                }
+                valid
+            })
+            .unwrap_or(DEFAULT_FIRMS_PERIOD.to_owned());
+
+        let bbox = env::var("FIRMS_BBOX").ok()
+            .and_then(|bbox| {
+                let values = bbox.split(',')
+                    .map(|v| v.trim().parse::<f64>().ok())
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|v| v.len() == 4 && v[0] < v[2] && v[1] < v[3]);
+                if values.is_none() {
+                    warn!("Invalid FIRMS bbox: {}", bbox);
+                }
+                values
+            })
+            .map(|v| [v[0], v[1], v[2], v[3]])
+            .unwrap_or(DEFAULT_BBOX);
+
+        FirmsConfig {
+            region: env::var("FIRMS_REGION").unwrap_or(DEFAULT_FIRMS_REGION.to_owned()),
+            period,
+            bbox,
+            min_confidence: env::var("FIRMS_MIN_CONFIDENCE").ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MIN_CONFIDENCE),
+        }
+    }
+
+    fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        latitude > self.bbox[0] && longitude > self.bbox[1]
+            && latitude < self.bbox[2] && longitude < self.bbox[3]
+    }
 }
 
 
 pub fn init_active_fire_sys(scheduler: &mut TaskSchedulerBuilder) {
     let delay = match get_fire_data() {
-        Ok(data) => {
-            update_fire_data(data);
+        Ok(records) => {
+            update_fire_data(records);
             Duration::new(60 * 15, 0)
         },
         Err(err) => {
             warn!("Fail to init active fire cache: {}", err);
 
-            update_fire_data(json!({
-                "fires": [],
-                "size": 0,
-            }).to_string());
+            update_fire_data(Vec::new());
 
             Duration::new(60 * 1, 0)
         }
@@ -48,17 +109,105 @@ pub fn init_active_fire_sys(scheduler: &mut TaskSchedulerBuilder) {
     scheduler.add_task(Task::new(active_fire_job, delay));
 }
 
-#[get("/active-fire-map")]
-pub fn get_active_fire_map() -> Json<String> {
-    Json(FIRE_DATA.read().unwrap().clone())
+/// `confidence` is 0~100 with VIIRS low, nominal and high as 0, 50 and 100.
+/// `satellite` is an instrument(`modis`, `viirs`) or a satellite name like `Aqua`.
+#[get("/active-fire-map?<confidence>&<satellite>&<min_power>")]
+pub fn get_active_fire_map(confidence: Option<f32>, satellite: Option<String>, min_power: Option<f32>)
+    -> Json<String> {
+
+    if confidence.is_none() && satellite.is_none() && min_power.is_none() {
+        return Json(FIRE_DATA.read().unwrap().clone());
+    }
+
+    let instrument = satellite.as_ref().and_then(|name| Instrument::from_name(name));
+
+    let records = FIRE_RECORDS.read().unwrap().iter()
+        .filter(|r| confidence.map_or(true, |conf| r.confidence >= conf))
+        .filter(|r| match (&satellite, instrument) {
+            (_, Some(instrument)) => r.instrument == instrument,
+            (Some(name), None) => r.satellite.eq_ignore_ascii_case(name),
+            (None, None) => true,
+        })
+        .filter(|r| min_power.map_or(true, |power| r.radiative_power >= power))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(make_fire_map_json(&records))
+}
+
+/// Saved hotspots between the KST dates `from` and `to` like `2019-04-05`, both inclusive.
+#[get("/active-fire-history?<from>&<to>&<district>")]
+pub fn get_active_fire_history(from: Option<String>, to: Option<String>, district: Option<String>)
+    -> Result<Json<String>, BadRequest<String>> {
+
+    let (from, to) = parse_date_range(from, to)?;
+
+    let hotspots = db::get_active_fire_hotspots_between(from, to, district.as_ref().map(|d| d.as_str()))
+        .map_err(|err| BadRequest(Some(err.to_string())))?;
+
+    let part_hotspots = hotspots.iter()
+        .map(|h| {
+            json!({
+                "latitude": h.latitude,
+                "longitude": h.longitude,
+                "bright": h.brightness,
+                "power": h.radiative_power,
+                "confidence": h.confidence,
+                "instrument": h.instrument,
+                "satellite": h.satellite,
+                "daynight": h.daynight,
+                "district": h.district,
+                "time": util::to_secs(h.detected_time),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "from": util::to_secs(from),
+        "to": util::to_secs(to),
+        "hotspots": part_hotspots,
+        "size": part_hotspots.len(),
+    }).to_string()))
+}
+
+/// Hotspot count and total FRP of each district by KST date.
+#[get("/active-fire-history/daily?<from>&<to>&<district>")]
+pub fn get_active_fire_daily(from: Option<String>, to: Option<String>, district: Option<String>)
+    -> Result<Json<String>, BadRequest<String>> {
+
+    let (from, to) = parse_date_range(from, to)?;
+
+    let daily = db::get_daily_active_fires_between(from, to, district.as_ref().map(|d| d.as_str()), util::KST_OFFSET)
+        .map_err(|err| BadRequest(Some(err.to_string())))?;
+
+    let part_daily = daily.iter()
+        .map(|d| {
+            json!({
+                "date": d.date,
+                "district": d.district,
+                "district_name": d.district.as_ref()
+                    .and_then(|code| district::get_district(code))
+                    .map(|d| &d.name),
+                "count": d.count,
+                "power": d.power,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "from": util::to_secs(from),
+        "to": util::to_secs(to),
+        "daily": part_daily,
+        "size": part_daily.len(),
+    }).to_string()))
 }
 
 fn active_fire_job() -> Duration {
     info!("Start job");
 
     match get_fire_data() {
-        Ok(json) => {
-            update_fire_data(json);
+        Ok(records) => {
+            update_fire_data(records);
             Duration::new(60 * 15, 0)
         },
         Err(err) => {
@@ -68,13 +217,98 @@ fn active_fire_job() -> Duration {
     }
 }
 
-fn update_fire_data(json: String) {
-    *FIRE_DATA.write().unwrap() = json;
+pub fn current_hotspots() -> Vec<Hotspot> {
+    FIRE_RECORDS.read().unwrap().iter()
+        .map(to_hotspot)
+        .collect()
+}
+
+/// Rebuild the map with the current official fire events.
+pub fn refresh_fire_map() {
+    let json = make_fire_map_json(&FIRE_RECORDS.read().unwrap());
+    event_sys::update_cache(&FIRE_DATA, json, Layer::ActiveFires);
+}
+
+
+fn to_hotspot(r: &FirmsRecord) -> Hotspot {
+    Hotspot {
+        latitude: r.latitude,
+        longitude: r.longitude,
+        time: r.time,
+    }
 }
 
-fn get_fire_data() -> Result<String, String> {
-    let modis = parse_fire_data("https://firms.modaps.eosdis.nasa.gov/data/active_fire/c6/csv/MODIS_C6_Russia_and_Asia_24h.csv");
-    let viirs = parse_fire_data("https://firms.modaps.eosdis.nasa.gov/data/active_fire/viirs/csv/VNP14IMGTDL_NRT_Russia_and_Asia_24h.csv");
+fn make_fire_map_json(records: &[FirmsRecord]) -> String {
+    let events = fire_sys::current_fire_events();
+
+    let hotspots = records.iter().map(to_hotspot).collect::<Vec<_>>();
+    let matches = fire_correlation::match_hotspots(&hotspots, &events);
+
+    let json_records = records.iter().zip(matches).map(|(r, matched)| {
+        json!({
+            "latitude": r.latitude,
+            "longitude": r.longitude,
+            "bright": r.brightness,
+            "power": r.radiative_power,
+            "confidence": r.confidence,
+            "instrument": r.instrument.name(),
+            "satellite": r.satellite,
+            "daynight": r.daynight.map(|dn| dn.name()),
+            "time": r.time,
+            "event": matched.map(|idx| &events[idx].id),
+            "unconfirmed": matched.is_none(),
+        })
+    }).collect::<Vec<_>>();
+
+    json!({
+        "fires": json_records,
+        "size": json_records.len(),
+    }).to_string()
+}
+
+
+fn update_fire_data(records: Vec<FirmsRecord>) {
+    save_fire_records(&records);
+
+    *FIRE_RECORDS.write().unwrap() = records;
+
+    refresh_fire_map();
+    fire_sys::refresh_fire_event_map();
+}
+
+fn save_fire_records(records: &[FirmsRecord]) {
+    let hotspots = records.iter()
+        .map(|r| db::models::NewActiveFireHotspot {
+            latitude: r.latitude,
+            longitude: r.longitude,
+            brightness: r.brightness,
+            radiative_power: r.radiative_power,
+            confidence: r.confidence,
+            instrument: r.instrument.name().to_owned(),
+            satellite: r.satellite.clone(),
+            daynight: r.daynight.map(|dn| dn.name().to_owned()),
+            district: district::find_district(r.latitude, r.longitude).map(|d| d.code.clone()),
+            detected_time: UNIX_EPOCH + Duration::new(r.time as u64, 0),
+        })
+        .collect::<Vec<_>>();
+
+    if hotspots.is_empty() {
+        return;
+    }
+
+    match db::insert_active_fire_hotspots(&hotspots) {
+        Ok(cnt) => info!("Save {} new hotspots", cnt),
+        Err(err) => warn!("Fail to save hotspots: {}", err),
+    }
+}
+
+fn get_fire_data() -> Result<Vec<FirmsRecord>, String> {
+    let config = &*FIRMS_CONFIG;
+
+    let modis = parse_fire_data(&format!("https://firms.modaps.eosdis.nasa.gov/data/active_fire/c6/csv/MODIS_C6_{}_{}.csv",
+        config.region, config.period));
+    let viirs = parse_fire_data(&format!("https://firms.modaps.eosdis.nasa.gov/data/active_fire/viirs/csv/VNP14IMGTDL_NRT_{}_{}.csv",
+        config.region, config.period));
 
     let records = match (modis, viirs) {
         (Ok(mut m), Ok(mut v)) => {
@@ -92,66 +326,130 @@ fn get_fire_data() -> Result<String, String> {
         (Err(err), Err(_)) => return Err(err),
     };
 
-    let json_records = records.into_iter().map(|r| {
-        json!({
-            "latitude": r.latitude,
-            "longitude": r.longitude,
-            "bright": r.brightness,
-            "power": r.radiative_power,
-            "time": r.time,
-        })
-    }).collect::<Vec<_>>();
-
-    Ok(json!({
-        "fires": json_records,
-        "size": json_records.len(),
-    }).to_string())
+    Ok(records)
 }
 
-fn parse_fire_data(uri: &str) -> Result<Vec<FireRecord>, String> {
+fn parse_fire_data(uri: &str) -> Result<Vec<FirmsRecord>, String> {
     reqwest::get(uri)
         .and_then(|mut res| res.text())
         .map_err(|err| err.to_string())
-        .map(|csv| {
-            csv.lines().skip(1)
-                .map(|row| row.split(',').collect())
-                .filter(|records: &Vec<&str>| records.len() >= 12)
-                .filter(|records| {
-                    // Only high confidence data.
-                    match records[8] {
-                        "high" => true,
-                        _ => match records[8].parse::<i32>() {
-                            Ok(confidence) => (confidence >= 70),
-                            _ => false
-                        }
-                    }
-                })
-                .map(|records| {
-                    let lat_res = records[0].parse();
-                    let lon_res = records[1].parse();
-                    let bright_res = records[2].parse();
-                    let power_res = records[11].parse();
-
-                    let date_str = records[5];
-                    let time_str = format!("{:0>4}", records[6]);
-                    let date_time_str = format!("{} {}", date_str, time_str);
-                    let time_res = NaiveDateTime::parse_from_str(&date_time_str,
-                        "%Y-%m-%d %H%M");
-
-                    match (lat_res, lon_res, bright_res, power_res, time_res) {
-                        (Ok(lat), Ok(lon), Ok(bright), Ok(power), Ok(time)) => Some(FireRecord {
-                            latitude: lat,
-                            longitude: lon,
-                            brightness: bright,
-                            radiative_power: power,
-                            time: time.timestamp(),
-                        }),
-                        _ => None
-                    }
-                })
-                .filter_map(|opt| opt)
-                .filter(|r| r.latitude > 32.477024 && r.longitude > 123.825178
-                    && r.latitude < 39.322145 && r.longitude < 132.799568)
+        .and_then(|csv| firms_parser::parse_firms_csv(&csv).map_err(|err| err.to_string()))
+        .map(|records| {
+            records.into_iter()
+                .filter(|r| r.confidence >= FIRMS_CONFIG.min_confidence)
+                .filter(|r| FIRMS_CONFIG.contains(r.latitude, r.longitude))
                 .collect()
         })
-}
\ No newline at end of file
+}
+
+/// Start and end of KST dates, defaulting to the last week.
+fn parse_date_range(from: Option<String>, to: Option<String>) -> Result<(SystemTime, SystemTime), BadRequest<String>> {
+    let day = 24 * 60 * 60;
+
+    let to = match to {
+        Some(date) => parse_kst_date(&date)?,
+        None => {
+            let now = Utc::now().timestamp() + util::KST_OFFSET as i64;
+            now - now.rem_euclid(day) - util::KST_OFFSET as i64
+        },
+    } + day;
+    let from = match from {
+        Some(date) => parse_kst_date(&date)?,
+        None => to - DEFAULT_HISTORY_DAYS * day,
+    };
+
+    if from >= to {
+        return Err(BadRequest(Some("from must not be after to".into())));
+    }
+    if to - from > MAX_HISTORY_DAYS * day {
+        return Err(BadRequest(Some(format!("The range must be within {} days", MAX_HISTORY_DAYS))));
+    }
+    if from < 0 {
+        return Err(BadRequest(Some("Invalid date".into())));
+    }
+
+    Ok((UNIX_EPOCH + Duration::new(from as u64, 0), UNIX_EPOCH + Duration::new(to as u64, 0)))
+}
+
+fn parse_kst_date(date: &str) -> Result<i64, BadRequest<String>> {
+    util::kst()
+        .datetime_from_str(&format!("{} 00:00:00", date.trim()), "%Y-%m-%d %H:%M:%S")
+        .map(|time| time.timestamp())
+        .map_err(|_| BadRequest(Some(format!("Invalid date: {}", date))))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+    const APR_5_2019: u64 = 1554390000; // 2019-04-05 00:00 KST
+
+    fn range(from: Option<&str>, to: Option<&str>) -> Result<(u64, u64), BadRequest<String>> {
+        parse_date_range(from.map(|s| s.to_owned()), to.map(|s| s.to_owned()))
+            .map(|(from, to)| (util::to_secs(from), util::to_secs(to)))
+    }
+
+    fn error_message(result: Result<(u64, u64), BadRequest<String>>) -> String {
+        match result {
+            Err(BadRequest(Some(msg))) => msg,
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parse_kst_midnight() {
+        assert_eq!(parse_kst_date("2019-04-05").unwrap(), APR_5_2019 as i64);
+        assert_eq!(parse_kst_date(" 2019-04-05 ").unwrap(), APR_5_2019 as i64);
+    }
+
+    #[test]
+    fn reject_malformed_date() {
+        for &date in &["", "2019-04", "2019/04/05", "2019-02-30", "2019-04-05T00:00", "yesterday"] {
+            assert!(parse_kst_date(date).is_err(), "{}", date);
+        }
+
+        assert!(error_message(range(Some("2019-04-32"), None)).contains("2019-04-32"));
+        assert!(error_message(range(None, Some("20190405"))).contains("20190405"));
+    }
+
+    #[test]
+    fn default_range_is_last_week() {
+        let (from, to) = range(None, None).unwrap();
+        let now = Utc::now().timestamp() as u64;
+
+        assert_eq!(to - from, DEFAULT_HISTORY_DAYS as u64 * DAY);
+        assert_eq!((to + util::KST_OFFSET as u64) % DAY, 0);
+        assert!(to - DAY <= now && now < to);
+
+        // Only `to` moves the default start.
+        let (from, to) = range(None, Some("2019-04-05")).unwrap();
+        assert_eq!(to, APR_5_2019 + DAY);
+        assert_eq!(from, to - DEFAULT_HISTORY_DAYS as u64 * DAY);
+    }
+
+    #[test]
+    fn range_includes_both_dates() {
+        assert_eq!(range(Some("2019-04-01"), Some("2019-04-05")).unwrap(), (APR_5_2019 - 4 * DAY, APR_5_2019 + DAY));
+        assert_eq!(range(Some("2019-04-05"), Some("2019-04-05")).unwrap(), (APR_5_2019, APR_5_2019 + DAY));
+    }
+
+    #[test]
+    fn reject_from_after_to() {
+        assert_eq!(error_message(range(Some("2019-04-06"), Some("2019-04-05"))), "from must not be after to");
+    }
+
+    #[test]
+    fn reject_too_long_range() {
+        assert!(range(Some("2019-01-01"), Some("2020-01-01")).is_ok());
+
+        let msg = error_message(range(Some("2019-01-01"), Some("2020-01-02")));
+        assert!(msg.contains(&MAX_HISTORY_DAYS.to_string()), "{}", msg);
+    }
+
+    #[test]
+    fn reject_date_before_epoch() {
+        assert_eq!(error_message(range(Some("1960-01-01"), Some("1960-01-02"))), "Invalid date");
+    }
+}
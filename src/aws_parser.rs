@@ -0,0 +1,524 @@
+use std::fmt;
+
+use chrono::TimeZone;
+
+use crate::util;
+
+
+const COMPASS_POINTS: [&'static str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+    "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+];
+
+
+/// Observation of a station in the AWS table.
+/// Values are `None` if the column is missing or the station didn't report it.
+#[derive(Debug)]
+pub struct Observation {
+    pub stn_id: String,
+    pub wind_dir: Option<f64>, // degrees
+    pub wind_speed: Option<f64>, // m/s
+    pub temperature: Option<f64>, // celsius
+    pub humidity: Option<f64>, // percent
+    pub precipitation: Option<f64>, // mm of the day
+}
+
+
+#[derive(Debug)]
+pub enum ParseError {
+    NoTable,
+    NoHeader,
+    MissingColumn(&'static str),
+    ShortRow { row: usize, columns: usize },
+    BadNumber { row: usize, text: String },
+    NoData,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoTable => write!(f, "Can't find the observation table"),
+            ParseError::NoHeader => write!(f, "Can't find the header of the observation table"),
+            ParseError::MissingColumn(name) => write!(f, "Can't find the {} column", name),
+            ParseError::ShortRow { row, columns } => write!(f, "Row {} has only {} columns", row, columns),
+            ParseError::BadNumber { row, text } => write!(f, "Row {} has a bad wind value: {}", row, text),
+            ParseError::NoData => write!(f, "There is no observation in the table"),
+        }
+    }
+}
+
+
+struct Cell {
+    text: String,
+    colspan: usize,
+    rowspan: usize,
+}
+
+
+/// Column indices of the observation table.
+struct Columns {
+    stn_id: usize,
+    wind_dir: usize,
+    wind_speed: usize,
+    temperature: Option<usize>,
+    humidity: Option<usize>,
+    precipitation: Option<usize>,
+}
+
+impl Columns {
+    fn find(labels: &[String]) -> Result<Self, ParseError> {
+        Ok(Columns {
+            stn_id: find_column(labels, &[&["지점번호"], &["지점"]], &["지점명"])
+                .ok_or(ParseError::MissingColumn("station"))?,
+            wind_dir: find_column(labels, &[&["풍향", "10"], &["풍향"]], &[])
+                .ok_or(ParseError::MissingColumn("wind direction"))?,
+            wind_speed: find_column(labels, &[&["풍속", "10"], &["풍속"]], &[])
+                .ok_or(ParseError::MissingColumn("wind speed"))?,
+            temperature: find_column(labels, &[&["기온"]], &[]),
+            humidity: find_column(labels, &[&["습도"]], &[]),
+            precipitation: find_column(labels, &[&["강수", "일"], &["일강수"], &["강수"]], &[]),
+        })
+    }
+
+    fn len(&self) -> usize {
+        let required = self.stn_id.max(self.wind_dir).max(self.wind_speed);
+
+        [self.temperature, self.humidity, self.precipitation].iter()
+            .filter_map(|&col| col)
+            .fold(required, usize::max) + 1
+    }
+}
+
+
+/// Parse the station table of the AWS minutely observation page.
+/// Columns are located by their header names so reordered columns are read correctly.
+pub fn parse_observations(html: &str) -> Result<Vec<Observation>, ParseError> {
+    let lower = html.to_ascii_lowercase();
+
+    let mut result = Err(ParseError::NoTable);
+    let mut begin = 0;
+
+    // Use the first table which looks like the observation table.
+    while let Some(table_begin) = find_tag(&lower, begin, "table") {
+        let table_end = lower[table_begin..].find("</table")
+            .map(|offset| table_begin + offset)
+            .unwrap_or(lower.len());
+
+        result = parse_table(&html[table_begin..table_end], &lower[table_begin..table_end]);
+        if result.is_ok() {
+            break;
+        }
+
+        begin = table_end;
+    }
+
+    result
+}
+
+/// Observation time of the page like `2019.04.05.14:25` in KST as a unix timestamp.
+pub fn parse_observed_time(html: &str) -> Option<i64> {
+    let kst = util::kst();
+    let bytes = html.as_bytes();
+    let pattern = b"dddd.dd.dd.dd:dd";
+
+    (0..bytes.len().saturating_sub(pattern.len() - 1))
+        .filter(|&begin| {
+            pattern.iter().zip(&bytes[begin..])
+                .all(|(&p, &b)| if p == b'd' { b.is_ascii_digit() } else { p == b })
+        })
+        .filter_map(|begin| {
+            kst.datetime_from_str(&html[begin..(begin + pattern.len())], "%Y.%m.%d.%H:%M").ok()
+        })
+        .map(|time| time.timestamp())
+        .next()
+}
+
+
+fn parse_table(html: &str, lower: &str) -> Result<Vec<Observation>, ParseError> {
+    let rows = parse_rows(html, lower);
+
+    // Header rows are the rows before the first station row.
+    let header_len = rows.iter()
+        .position(|row| row.first().map_or(false, |cell| is_station_id(&cell.text)))
+        .ok_or(ParseError::NoHeader)?;
+
+    if header_len == 0 {
+        return Err(ParseError::NoHeader);
+    }
+
+    let columns = Columns::find(&make_labels(&rows[..header_len]))?;
+
+    let mut observations = Vec::new();
+
+    for (index, row) in rows.iter().enumerate().skip(header_len) {
+        let values = row.iter()
+            .flat_map(|cell| (0..cell.colspan).map(move |_| cell.text.as_str()))
+            .collect::<Vec<_>>();
+
+        if values.is_empty() || !is_station_id(values[0]) {
+            continue;
+        }
+
+        match parse_row(&values, &columns, index) {
+            Ok(observation) => observations.push(observation),
+            Err(err) => warn!("Skip AWS row: {}", err),
+        }
+    }
+
+    if observations.is_empty() {
+        Err(ParseError::NoData)
+    }
+    else {
+        Ok(observations)
+    }
+}
+
+/// Observation of a station row. Missing values are `None` but a broken wind value is an error.
+fn parse_row(values: &[&str], columns: &Columns, index: usize) -> Result<Observation, ParseError> {
+    if values.len() < columns.len() {
+        return Err(ParseError::ShortRow { row: index, columns: values.len() });
+    }
+
+    let value = |col: Option<usize>| col.and_then(|col| parse_value(values[col]));
+    let wind_value = |col: usize, parse: fn(&str) -> Option<f64>| {
+        let text = values[col];
+
+        match parse(text) {
+            Some(val) => Ok(Some(val)),
+            None if is_missing(text) => Ok(None),
+            None => Err(ParseError::BadNumber { row: index, text: text.to_owned() }),
+        }
+    };
+
+    Ok(Observation {
+        stn_id: values[columns.stn_id].to_owned(),
+        wind_dir: wind_value(columns.wind_dir, parse_direction)?,
+        wind_speed: wind_value(columns.wind_speed, parse_value)?,
+        temperature: value(columns.temperature),
+        humidity: value(columns.humidity),
+        precipitation: value(columns.precipitation),
+    })
+}
+
+/// Find `<name` followed by the end of the tag name.
+fn find_tag(lower: &str, mut begin: usize, name: &str) -> Option<usize> {
+    let pattern = format!("<{}", name);
+
+    while let Some(offset) = lower[begin..].find(&pattern) {
+        let idx = begin + offset;
+
+        match lower[(idx + pattern.len())..].chars().next() {
+            Some(ch) if ch == '>' || ch == '/' || ch.is_whitespace() => return Some(idx),
+            _ => begin = idx + pattern.len(),
+        }
+    }
+
+    None
+}
+
+fn parse_span(tag: &str, name: &str) -> usize {
+    tag.find(name)
+        .map(|idx| &tag[(idx + name.len())..])
+        .map(|rest| rest.trim_start_matches(|ch: char| ch == '=' || ch == '"' || ch == '\'' || ch.is_whitespace()))
+        .and_then(|rest| {
+            let end = rest.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(rest.len());
+            rest[..end].parse().ok()
+        })
+        .filter(|&span| span > 0)
+        .unwrap_or(1)
+}
+
+fn parse_rows(html: &str, lower: &str) -> Vec<Vec<Cell>> {
+    let mut rows = Vec::new();
+    let mut begin = 0;
+
+    while let Some(row_begin) = find_tag(lower, begin, "tr") {
+        let next_row = find_tag(lower, row_begin + 1, "tr").unwrap_or(lower.len());
+        let row_end = lower[row_begin..next_row].find("</tr")
+            .map(|offset| row_begin + offset)
+            .unwrap_or(next_row);
+
+        let mut row = Vec::new();
+        let mut cell_begin = row_begin;
+
+        loop {
+            let td = find_tag(&lower[..row_end], cell_begin, "td");
+            let th = find_tag(&lower[..row_end], cell_begin, "th");
+
+            let tag_begin = match (td, th) {
+                (Some(td), Some(th)) => td.min(th),
+                (Some(idx), None) | (None, Some(idx)) => idx,
+                (None, None) => break,
+            };
+
+            let tag_end = match lower[tag_begin..row_end].find('>') {
+                Some(offset) => tag_begin + offset,
+                None => break,
+            };
+            let cell_end = lower[tag_end..row_end].find("</t")
+                .map(|offset| tag_end + offset)
+                .unwrap_or(row_end);
+
+            let tag = &lower[tag_begin..tag_end];
+
+            row.push(Cell {
+                text: util::extract_text_from_html(&html[(tag_end + 1)..cell_end])
+                    .replace("&nbsp;", " ")
+                    .trim()
+                    .to_owned(),
+                colspan: parse_span(tag, "colspan"),
+                rowspan: parse_span(tag, "rowspan"),
+            });
+
+            cell_begin = cell_end;
+        }
+
+        if !row.is_empty() {
+            rows.push(row);
+        }
+
+        begin = next_row;
+    }
+
+    rows
+}
+
+/// Join the header texts of each column, following colspan and rowspan.
+fn make_labels(rows: &[Vec<Cell>]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut occupied: Vec<usize> = Vec::new();
+
+    for row in rows {
+        let mut col = 0;
+
+        for cell in row {
+            while col < occupied.len() && occupied[col] > 0 {
+                col += 1;
+            }
+
+            for c in col..(col + cell.colspan) {
+                if c >= labels.len() {
+                    labels.resize(c + 1, String::new());
+                    occupied.resize(c + 1, 0);
+                }
+
+                if !cell.text.is_empty() {
+                    if !labels[c].is_empty() {
+                        labels[c].push(' ');
+                    }
+                    labels[c].push_str(&cell.text);
+                }
+
+                occupied[c] = cell.rowspan;
+            }
+
+            col += cell.colspan;
+        }
+
+        for rows_left in occupied.iter_mut() {
+            *rows_left = rows_left.saturating_sub(1);
+        }
+    }
+
+    labels
+}
+
+/// Index of the first column matching all keywords of a candidate, in the order of candidates.
+fn find_column(labels: &[String], candidates: &[&[&str]], excludes: &[&str]) -> Option<usize> {
+    candidates.iter()
+        .filter_map(|keywords| {
+            labels.iter().position(|label| {
+                keywords.iter().all(|kw| label.contains(kw))
+                    && !excludes.iter().any(|ex| label.contains(ex))
+            })
+        })
+        .next()
+}
+
+fn is_station_id(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|ch| ch.is_ascii_digit())
+}
+
+/// Placeholders of the values a station didn't report.
+fn is_missing(text: &str) -> bool {
+    match text.trim() {
+        "" | "-" | "." | "=" => true,
+        _ => false,
+    }
+}
+
+fn parse_value(text: &str) -> Option<f64> {
+    text.trim().parse::<f64>().ok()
+        .filter(|val| val.is_finite())
+}
+
+/// Direction in degrees, or a 16-point compass name like `NNE`.
+fn parse_direction(text: &str) -> Option<f64> {
+    parse_value(text).or_else(|| {
+        COMPASS_POINTS.iter()
+            .position(|&name| name.eq_ignore_ascii_case(text.trim()))
+            .map(|idx| idx as f64 * 22.5)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AWS_PAGE: &str = include_str!("../tests/fixtures/aws.html");
+    const REORDERED_PAGE: &str = include_str!("../tests/fixtures/aws_reordered.html");
+    const OBSERVED_TIME: i64 = 1554441900; // 2019-04-05 14:25 KST
+
+    fn find_observation<'a>(observations: &'a [Observation], stn_id: &str) -> &'a Observation {
+        observations.iter().find(|obs| obs.stn_id == stn_id).unwrap()
+    }
+
+    fn header_labels(html: &str) -> Vec<String> {
+        let begin = html.find("<table border").unwrap();
+        let rows = parse_rows(&html[begin..], &html[begin..].to_ascii_lowercase());
+
+        make_labels(&rows[..2])
+    }
+
+    #[test]
+    fn labels_follow_colspan_and_rowspan() {
+        let labels = header_labels(AWS_PAGE);
+
+        assert_eq!(labels.len(), 20);
+        assert_eq!(labels[0], "지점번호");
+        assert_eq!(labels[9], "강수 일");
+        assert_eq!(labels[10], "기온(℃)");
+        assert_eq!(labels[14], "10분 평균 풍향");
+        assert_eq!(labels[16], "10분 평균 풍속(m/s)");
+        assert_eq!(labels[17], "습도(%)");
+    }
+
+    #[test]
+    fn find_column_by_header() {
+        let labels = header_labels(AWS_PAGE);
+
+        assert_eq!(find_column(&labels, &[&["지점번호"], &["지점"]], &["지점명"]), Some(0));
+        assert_eq!(find_column(&labels, &[&["풍향", "10"], &["풍향"]], &[]), Some(14));
+        assert_eq!(find_column(&labels, &[&["풍속", "10"], &["풍속"]], &[]), Some(16));
+        assert_eq!(find_column(&labels, &[&["강수", "일"], &["일강수"], &["강수"]], &[]), Some(9));
+        assert_eq!(find_column(&labels, &[&["일사"]], &[]), None);
+
+        let columns = Columns::find(&labels).unwrap();
+        assert_eq!((columns.stn_id, columns.wind_dir, columns.wind_speed), (0, 14, 16));
+        assert_eq!((columns.temperature, columns.humidity, columns.precipitation), (Some(10), Some(17), Some(9)));
+    }
+
+    #[test]
+    fn parse_aws_page() {
+        let observations = parse_observations(AWS_PAGE).unwrap();
+        assert_eq!(observations.len(), 4);
+
+        let sokcho = find_observation(&observations, "90");
+        assert_eq!(sokcho.wind_dir, Some(251.2));
+        assert_eq!(sokcho.wind_speed, Some(7.8));
+        assert_eq!(sokcho.temperature, Some(12.3));
+        assert_eq!(sokcho.humidity, Some(18.0));
+        assert_eq!(sokcho.precipitation, Some(0.5));
+
+        // A station which didn't report the wind.
+        let cheorwon = find_observation(&observations, "95");
+        assert_eq!(cheorwon.wind_dir, None);
+        assert_eq!(cheorwon.wind_speed, None);
+        assert_eq!(cheorwon.humidity, None);
+        assert_eq!(cheorwon.temperature, Some(13.8));
+    }
+
+    #[test]
+    fn parse_reordered_columns() {
+        let observations = parse_observations(REORDERED_PAGE).unwrap();
+        assert_eq!(observations.len(), 2);
+
+        let sokcho = find_observation(&observations, "90");
+        assert_eq!(sokcho.wind_dir, Some(247.5)); // WSW
+        assert_eq!(sokcho.wind_speed, Some(7.8));
+        assert_eq!(sokcho.temperature, Some(12.3));
+        assert_eq!(sokcho.humidity, Some(18.0));
+        assert_eq!(sokcho.precipitation, Some(0.5));
+
+        let chuncheon = find_observation(&observations, "93");
+        assert_eq!(chuncheon.wind_dir, Some(275.0));
+        assert_eq!(chuncheon.wind_speed, Some(2.5));
+    }
+
+    #[test]
+    fn parse_time_in_kst() {
+        assert_eq!(parse_observed_time(AWS_PAGE), Some(OBSERVED_TIME));
+        assert_eq!(parse_observed_time(REORDERED_PAGE), Some(OBSERVED_TIME));
+        assert_eq!(parse_observed_time("2019.13.05.14:25"), None);
+        assert_eq!(parse_observed_time("<html></html>"), None);
+    }
+
+    #[test]
+    fn no_table() {
+        match parse_observations("<html><body>점검 중입니다</body></html>") {
+            Err(ParseError::NoTable) => (),
+            _ => panic!("expected NoTable"),
+        }
+    }
+
+    #[test]
+    fn no_header() {
+        match parse_observations("<table><tr><td>90</td><td>7.8</td></tr></table>") {
+            Err(ParseError::NoHeader) => (),
+            _ => panic!("expected NoHeader"),
+        }
+    }
+
+    #[test]
+    fn missing_column() {
+        let html = REORDERED_PAGE.replace("풍속 10분", "순간최대");
+
+        match parse_observations(&html) {
+            Err(ParseError::MissingColumn("wind speed")) => (),
+            _ => panic!("expected MissingColumn"),
+        }
+    }
+
+    #[test]
+    fn no_data() {
+        let html = "<table><tr><th>지점</th><th>풍향</th><th>풍속</th></tr></table>";
+
+        match parse_observations(html) {
+            Err(ParseError::NoHeader) => (),
+            _ => panic!("expected NoHeader"),
+        }
+
+        let html = "<table><tr><th>지점</th><th>풍향</th><th>풍속</th></tr><tr><td>90</td><td>=</td><td>abc</td></tr></table>";
+
+        match parse_observations(html) {
+            Err(ParseError::NoData) => (),
+            _ => panic!("expected NoData"),
+        }
+    }
+
+    #[test]
+    fn short_row_and_bad_number() {
+        let columns = Columns::find(&header_labels(AWS_PAGE)).unwrap();
+
+        match parse_row(&["90", "속초", "18"], &columns, 3) {
+            Err(ParseError::ShortRow { row: 3, columns: 3 }) => (),
+            _ => panic!("expected ShortRow"),
+        }
+
+        let mut values = vec!["0"; 20];
+        values[0] = "90";
+        values[16] = "7.8m";
+
+        match parse_row(&values, &columns, 4) {
+            Err(ParseError::BadNumber { row: 4, ref text }) if text == "7.8m" => (),
+            _ => panic!("expected BadNumber"),
+        }
+
+        // The other stations are still read.
+        let html = AWS_PAGE.replace("<td>7.8</td>", "<td>7.8m</td>");
+        let observations = parse_observations(&html).unwrap();
+        assert_eq!(observations.len(), 3);
+        assert!(observations.iter().all(|obs| obs.stn_id != "90"));
+    }
+}
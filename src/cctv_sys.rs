@@ -18,6 +18,7 @@ use quick_xml::{
 };
 use serde_json::json;
 
+use crate::event_sys::{self, Layer};
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
 
@@ -134,9 +135,7 @@ fn cctv_job() -> Duration {
 }
 
 fn update_cctv_cache(cctvs: Vec<CctvData>) {
-    {
-        *CCTV_DATA.write().unwrap() = stringify_cctvs(&cctvs);
-    }
+    event_sys::update_cache(&CCTV_DATA, stringify_cctvs(&cctvs), Layer::Cctv);
 
     for tv in cctvs {
         let mut list = CCTV_LIST.write().unwrap();
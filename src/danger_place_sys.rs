@@ -4,6 +4,7 @@ use rocket::response::content::Json;
 use serde_json::json;
 
 use crate::TaskSchedulerBuilder;
+use crate::event_sys::{self, Layer};
 
 
 lazy_static! {
@@ -46,5 +47,5 @@ fn update_danger_place_map() {
         "size": places.len(),
     }).to_string();
     
-    *PLACE_MAP_CACHE.write().unwrap() = map_data;
+    event_sys::update_cache(&PLACE_MAP_CACHE, map_data, Layer::DangerPlaces);
 }
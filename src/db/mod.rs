@@ -3,11 +3,13 @@ pub mod schema;
 
 
 use std::env;
-use std::time::{UNIX_EPOCH, Duration};
+use std::time::{UNIX_EPOCH, Duration, SystemTime};
 
 use diesel::prelude::*;
 use diesel::pg::PgConnection;
+use diesel::pg::upsert::excluded;
 use diesel::result::QueryResult;
+use diesel::sql_types::{Integer, Nullable, Text, Timestamp};
 
 use chrono::Utc;
 
@@ -16,6 +18,11 @@ use schema::reports::dsl as r_dsl;
 use schema::bad_reports::dsl as bad_dsl;
 use schema::shelters::dsl as shelter_dsl;
 use schema::user_shelters::dsl as us_dsl;
+use schema::forecast_history::dsl as fh_dsl;
+use schema::wind_observations::dsl as wo_dsl;
+use schema::fire_events::dsl as fe_dsl;
+use schema::fire_event_changes::dsl as fec_dsl;
+use schema::active_fire_hotspots::dsl as afh_dsl;
 
 
 thread_local! {
@@ -141,3 +148,207 @@ pub fn delete_user_shelter(id: i32) -> QueryResult<usize> {
             .execute(conn)
     })
 }
+
+
+pub fn insert_forecast_history(history: &[NewForecastHistory]) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        diesel::insert_into(schema::forecast_history::table)
+            .values(history)
+            .execute(conn)
+    })
+}
+
+pub fn get_forecast_history(code: &str, since: SystemTime) -> QueryResult<Vec<ForecastHistory>> {
+    DB_CONN.with(|conn| {
+        fh_dsl::forecast_history
+            .filter(fh_dsl::code.eq(code))
+            .filter(fh_dsl::fetched_time.gt(since))
+            .order(fh_dsl::fetched_time.asc())
+            .load::<ForecastHistory>(conn)
+    })
+}
+
+pub fn insert_wind_observations(observations: &[NewWindObservation]) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        diesel::insert_into(schema::wind_observations::table)
+            .values(observations)
+            .execute(conn)
+    })
+}
+
+pub fn get_wind_observation_times(since: SystemTime) -> QueryResult<Vec<SystemTime>> {
+    DB_CONN.with(|conn| {
+        wo_dsl::wind_observations
+            .select(wo_dsl::observed_time)
+            .filter(wo_dsl::observed_time.gt(since))
+            .distinct()
+            .order(wo_dsl::observed_time.asc())
+            .load::<SystemTime>(conn)
+    })
+}
+
+pub fn get_wind_observations_at(time: SystemTime) -> QueryResult<Vec<WindObservation>> {
+    DB_CONN.with(|conn| {
+        wo_dsl::wind_observations
+            .filter(wo_dsl::observed_time.eq(time))
+            .load::<WindObservation>(conn)
+    })
+}
+
+pub fn delete_wind_observations_before(time: SystemTime) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        diesel::delete(wo_dsl::wind_observations.filter(wo_dsl::observed_time.lt(time)))
+            .execute(conn)
+    })
+}
+
+/// Insert new events and update the others, keeping `first_seen`.
+/// Active events not in `events` are marked inactive.
+pub fn upsert_fire_events(events: &[NewFireEvent], seen_time: SystemTime) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        conn.transaction(|| {
+            if !events.is_empty() {
+                diesel::insert_into(schema::fire_events::table)
+                    .values(events)
+                    .on_conflict(fe_dsl::id)
+                    .do_update()
+                    .set((
+                        fe_dsl::status_code.eq(excluded(fe_dsl::status_code)),
+                        fe_dsl::latitude.eq(excluded(fe_dsl::latitude)),
+                        fe_dsl::longitude.eq(excluded(fe_dsl::longitude)),
+                        fe_dsl::address.eq(excluded(fe_dsl::address)),
+                        fe_dsl::district.eq(excluded(fe_dsl::district)),
+                        fe_dsl::extinguished_date.eq(excluded(fe_dsl::extinguished_date)),
+                        fe_dsl::extinguished_time.eq(excluded(fe_dsl::extinguished_time)),
+                        fe_dsl::damage_area.eq(excluded(fe_dsl::damage_area)),
+                        fe_dsl::cause.eq(excluded(fe_dsl::cause)),
+                        fe_dsl::last_seen.eq(excluded(fe_dsl::last_seen)),
+                        fe_dsl::active.eq(true),
+                    ))
+                    .execute(conn)?;
+            }
+
+            diesel::update(fe_dsl::fire_events
+                    .filter(fe_dsl::active.eq(true))
+                    .filter(fe_dsl::last_seen.lt(seen_time)))
+                .set(fe_dsl::active.eq(false))
+                .execute(conn)
+        })
+    })
+}
+
+pub fn get_fire_event(id: &str) -> QueryResult<Option<FireEvent>> {
+    DB_CONN.with(|conn| {
+        fe_dsl::fire_events
+            .find(id)
+            .first(conn)
+            .optional()
+    })
+}
+
+pub fn get_fire_events_since(since: SystemTime, district: Option<&str>) -> QueryResult<Vec<FireEvent>> {
+    DB_CONN.with(|conn| {
+        let mut query = fe_dsl::fire_events
+            .filter(fe_dsl::last_seen.gt(since))
+            .order(fe_dsl::first_seen.desc())
+            .into_boxed();
+
+        if let Some(district) = district {
+            query = query.filter(fe_dsl::district.eq(district));
+        }
+
+        query.load::<FireEvent>(conn)
+    })
+}
+
+pub fn get_active_fire_events() -> QueryResult<Vec<FireEvent>> {
+    DB_CONN.with(|conn| {
+        fe_dsl::fire_events
+            .filter(fe_dsl::active.eq(true))
+            .load::<FireEvent>(conn)
+    })
+}
+
+pub fn insert_fire_event_changes(changes: &[NewFireEventChange]) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        diesel::insert_into(schema::fire_event_changes::table)
+            .values(changes)
+            .execute(conn)
+    })
+}
+
+/// Changes after the change `after` if it's given, otherwise the ones after `since`.
+pub fn get_fire_event_changes_after(since: SystemTime, after: Option<i32>, limit: i64)
+    -> QueryResult<Vec<FireEventChange>> {
+
+    DB_CONN.with(|conn| {
+        let mut query = fec_dsl::fire_event_changes
+            .order(fec_dsl::id.asc())
+            .limit(limit)
+            .into_boxed();
+
+        query = match after {
+            Some(after) => query.filter(fec_dsl::id.gt(after)),
+            None => query.filter(fec_dsl::changed_time.gt(since)),
+        };
+
+        query.load::<FireEventChange>(conn)
+    })
+}
+
+/// Insert hotspots skipping ones already saved from an earlier file.
+/// Rows are inserted in chunks to stay under the bind parameter limit of a query.
+pub fn insert_active_fire_hotspots(hotspots: &[NewActiveFireHotspot]) -> QueryResult<usize> {
+    DB_CONN.with(|conn| {
+        conn.transaction(|| {
+            let mut cnt = 0;
+
+            for chunk in hotspots.chunks(1000) {
+                cnt += diesel::insert_into(schema::active_fire_hotspots::table)
+                    .values(chunk)
+                    .on_conflict((afh_dsl::latitude, afh_dsl::longitude, afh_dsl::detected_time, afh_dsl::satellite))
+                    .do_nothing()
+                    .execute(conn)?;
+            }
+
+            Ok(cnt)
+        })
+    })
+}
+
+pub fn get_active_fire_hotspots_between(from: SystemTime, to: SystemTime, district: Option<&str>)
+    -> QueryResult<Vec<ActiveFireHotspot>> {
+
+    DB_CONN.with(|conn| {
+        let mut query = afh_dsl::active_fire_hotspots
+            .filter(afh_dsl::detected_time.ge(from))
+            .filter(afh_dsl::detected_time.lt(to))
+            .order(afh_dsl::detected_time.asc())
+            .into_boxed();
+
+        if let Some(district) = district {
+            query = query.filter(afh_dsl::district.eq(district));
+        }
+
+        query.load::<ActiveFireHotspot>(conn)
+    })
+}
+
+/// Hotspot count and total FRP grouped by the date in `utc_offset` and the district.
+pub fn get_daily_active_fires_between(from: SystemTime, to: SystemTime, district: Option<&str>, utc_offset: i32)
+    -> QueryResult<Vec<DailyActiveFire>> {
+
+    DB_CONN.with(|conn| {
+        diesel::sql_query("select to_char(detected_time + $4 * interval '1 second', 'YYYY-MM-DD') as date, \
+                district, count(*) as count, sum(radiative_power)::double precision as power \
+            from active_fire_hotspots \
+            where detected_time >= $1 and detected_time < $2 and ($3::text is null or district = $3) \
+            group by 1, 2 \
+            order by 1, 2 nulls first")
+            .bind::<Timestamp, _>(from)
+            .bind::<Timestamp, _>(to)
+            .bind::<Nullable<Text>, _>(district)
+            .bind::<Integer, _>(utc_offset)
+            .load::<DailyActiveFire>(conn)
+    })
+}
@@ -1,4 +1,5 @@
 use std::time::SystemTime;
+use diesel::sql_types::{BigInt, Double, Nullable, Text};
 use super::schema::*;
 
 
@@ -82,4 +83,144 @@ pub struct NewUserShelter {
     pub longitude: f64,
     pub info: String,
     pub evidence: String,
-}
\ No newline at end of file
+}
+
+#[derive(Queryable)]
+pub struct ForecastHistory {
+    pub id: i32,
+    pub code: String,
+    pub lvl: f32,
+    pub fetched_time: SystemTime,
+}
+
+#[derive(Insertable)]
+#[table_name="forecast_history"]
+pub struct NewForecastHistory {
+    pub code: String,
+    pub lvl: f32,
+    pub fetched_time: SystemTime,
+}
+
+#[derive(Queryable)]
+pub struct WindObservation {
+    pub id: i32,
+    pub stn_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub wind_x: f64,
+    pub wind_y: f64,
+    pub observed_time: SystemTime,
+}
+
+#[derive(Insertable)]
+#[table_name="wind_observations"]
+pub struct NewWindObservation {
+    pub stn_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub wind_x: f64,
+    pub wind_y: f64,
+    pub observed_time: SystemTime,
+}
+
+#[derive(Queryable)]
+pub struct FireEvent {
+    pub id: String,
+    pub status_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub address: String,
+    pub district: Option<String>,
+    pub reported_date: String,
+    pub reported_time: String,
+    pub extinguished_date: Option<String>,
+    pub extinguished_time: Option<String>,
+    pub damage_area: Option<f64>,
+    pub cause: Option<String>,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    pub active: bool,
+}
+
+#[derive(Insertable)]
+#[table_name="fire_events"]
+pub struct NewFireEvent {
+    pub id: String,
+    pub status_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub address: String,
+    pub district: Option<String>,
+    pub reported_date: String,
+    pub reported_time: String,
+    pub extinguished_date: Option<String>,
+    pub extinguished_time: Option<String>,
+    pub damage_area: Option<f64>,
+    pub cause: Option<String>,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+    pub active: bool,
+}
+
+#[derive(Queryable)]
+pub struct FireEventChange {
+    pub id: i32,
+    pub event_id: String,
+    pub kind: String,
+    pub old_code: Option<String>,
+    pub new_code: Option<String>,
+    pub changed_time: SystemTime,
+}
+
+#[derive(Insertable)]
+#[table_name="fire_event_changes"]
+pub struct NewFireEventChange {
+    pub event_id: String,
+    pub kind: String,
+    pub old_code: Option<String>,
+    pub new_code: Option<String>,
+    pub changed_time: SystemTime,
+}
+
+#[derive(Queryable)]
+pub struct ActiveFireHotspot {
+    pub id: i32,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub brightness: f32,
+    pub radiative_power: f32,
+    pub confidence: f32,
+    pub instrument: String,
+    pub satellite: String,
+    pub daynight: Option<String>,
+    pub district: Option<String>,
+    pub detected_time: SystemTime,
+}
+
+#[derive(Insertable)]
+#[table_name="active_fire_hotspots"]
+pub struct NewActiveFireHotspot {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub brightness: f32,
+    pub radiative_power: f32,
+    pub confidence: f32,
+    pub instrument: String,
+    pub satellite: String,
+    pub daynight: Option<String>,
+    pub district: Option<String>,
+    pub detected_time: SystemTime,
+}
+
+/// Hotspots of a district in a KST date.
+#[derive(QueryableByName)]
+pub struct DailyActiveFire {
+    #[sql_type = "Text"]
+    pub date: String,
+    #[sql_type = "Nullable<Text>"]
+    pub district: Option<String>,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+    #[sql_type = "Double"]
+    pub power: f64,
+}
@@ -41,4 +41,72 @@ table! {
         info -> Text,
         evidence -> Text,
     }
+}
+
+table! {
+    forecast_history (id) {
+        id -> Integer,
+        code -> Text,
+        lvl -> Float,
+        fetched_time -> Timestamp,
+    }
+}
+
+table! {
+    wind_observations (id) {
+        id -> Integer,
+        stn_id -> Text,
+        latitude -> Double,
+        longitude -> Double,
+        wind_x -> Double,
+        wind_y -> Double,
+        observed_time -> Timestamp,
+    }
+}
+
+table! {
+    fire_events (id) {
+        id -> Text,
+        status_code -> Text,
+        latitude -> Double,
+        longitude -> Double,
+        address -> Text,
+        district -> Nullable<Text>,
+        reported_date -> Text,
+        reported_time -> Text,
+        extinguished_date -> Nullable<Text>,
+        extinguished_time -> Nullable<Text>,
+        damage_area -> Nullable<Double>,
+        cause -> Nullable<Text>,
+        first_seen -> Timestamp,
+        last_seen -> Timestamp,
+        active -> Bool,
+    }
+}
+
+table! {
+    fire_event_changes (id) {
+        id -> Integer,
+        event_id -> Text,
+        kind -> Text,
+        old_code -> Nullable<Text>,
+        new_code -> Nullable<Text>,
+        changed_time -> Timestamp,
+    }
+}
+
+table! {
+    active_fire_hotspots (id) {
+        id -> Integer,
+        latitude -> Double,
+        longitude -> Double,
+        brightness -> Float,
+        radiative_power -> Float,
+        confidence -> Float,
+        instrument -> Text,
+        satellite -> Text,
+        daynight -> Nullable<Text>,
+        district -> Nullable<Text>,
+        detected_time -> Timestamp,
+    }
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use std::fs;
+
+use serde_json::Value as JsonValue;
+
+
+lazy_static! {
+    static ref DISTRICTS: Vec<District> = {
+        // Without the boundaries everything still works, just with no district.
+        let text = match fs::read_to_string(DISTRICT_BOUNDARY_FILE) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("Fail to open {}: {}. Run scripts/make_district_boundary.py",
+                    DISTRICT_BOUNDARY_FILE, err);
+                return Vec::new();
+            },
+        };
+
+        let districts = parse_districts(&text)
+            .unwrap_or_else(|err| panic!("Fail to parse {}: {}", DISTRICT_BOUNDARY_FILE, err));
+
+        if districts.is_empty() {
+            warn!("There is no district in {}", DISTRICT_BOUNDARY_FILE);
+        }
+
+        districts
+    };
+}
+
+const DISTRICT_BOUNDARY_FILE: &'static str = "data/district_boundary.json";
+
+
+type Ring = Vec<(f64, f64)>;
+
+
+pub struct District {
+    pub code: String,
+    pub name: String,
+    pub geometry: JsonValue,
+    polygons: Vec<Vec<Ring>>,
+    min_lon: f64,
+    min_lat: f64,
+    max_lon: f64,
+    max_lat: f64,
+}
+
+impl District {
+    fn contains(&self, longitude: f64, latitude: f64) -> bool {
+        if longitude < self.min_lon || longitude > self.max_lon
+            || latitude < self.min_lat || latitude > self.max_lat {
+            return false;
+        }
+
+        // Even-odd rule over every ring so holes are handled too.
+        self.polygons.iter().any(|rings| {
+            rings.iter()
+                .filter(|ring| ring_contains(ring, longitude, latitude))
+                .count() % 2 == 1
+        })
+    }
+}
+
+
+pub fn init_district() {
+    lazy_static::initialize(&DISTRICTS);
+    info!("{} districts loaded", DISTRICTS.len());
+}
+
+pub fn get_district(code: &str) -> Option<&'static District> {
+    DISTRICTS.iter().find(|d| d.code == code)
+}
+
+pub fn find_district(latitude: f64, longitude: f64) -> Option<&'static District> {
+    DISTRICTS.iter().find(|d| d.contains(longitude, latitude))
+}
+
+
+fn ring_contains(ring: &Ring, x: f64, y: f64) -> bool {
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+fn parse_ring(value: &JsonValue) -> Option<Ring> {
+    value.as_array()?
+        .iter()
+        .map(|pt| {
+            let pt = pt.as_array()?;
+            Some((pt.get(0)?.as_f64()?, pt.get(1)?.as_f64()?))
+        })
+        .collect()
+}
+
+fn parse_polygon(value: &JsonValue) -> Option<Vec<Ring>> {
+    value.as_array()?
+        .iter()
+        .map(parse_ring)
+        .collect()
+}
+
+fn parse_districts(text: &str) -> Result<Vec<District>, String> {
+    let data: JsonValue = serde_json::from_str(text)
+        .map_err(|err| err.to_string())?;
+    let features = data["features"].as_array()
+        .ok_or("Can't find features property".to_owned())?;
+
+    let mut districts = Vec::with_capacity(features.len());
+
+    for feature in features {
+        let code = feature["properties"]["code"].as_str();
+        let name = feature["properties"]["name"].as_str();
+        let geometry = &feature["geometry"];
+
+        let polygons = match geometry["type"].as_str() {
+            Some("Polygon") => parse_polygon(&geometry["coordinates"]).map(|p| vec![p]),
+            Some("MultiPolygon") => geometry["coordinates"].as_array()
+                .and_then(|arr| arr.iter().map(parse_polygon).collect()),
+            _ => None,
+        };
+
+        if let (Some(code), Some(name), Some(polygons)) = (code, name, polygons) {
+            let points = polygons.iter().flatten().flatten();
+
+            let (min_lon, min_lat, max_lon, max_lat) = points.fold(
+                (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+                |acc, &(x, y)| (acc.0.min(x), acc.1.min(y), acc.2.max(x), acc.3.max(y)));
+
+            districts.push(District {
+                code: code.to_owned(),
+                name: name.to_owned(),
+                geometry: geometry.clone(),
+                polygons,
+                min_lon,
+                min_lat,
+                max_lon,
+                max_lat,
+            });
+        }
+        else {
+            warn!("Skip invalid district feature");
+        }
+    }
+
+    Ok(districts)
+}
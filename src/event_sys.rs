@@ -0,0 +1,195 @@
+use std::{
+    env,
+    io::{self, Read},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, RecvTimeoutError, TrySendError},
+        atomic::{AtomicUsize, Ordering},
+        Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use rocket::{
+    http::{ContentType, Status},
+    response::{
+        Content,
+        Stream,
+        status::Custom,
+    },
+};
+use serde_json::json;
+use chrono::Utc;
+
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<SyncSender<String>>> = {
+        Mutex::new(Vec::new())
+    };
+}
+
+static CLIENT_CNT: AtomicUsize = AtomicUsize::new(0);
+static MAX_CLIENTS: AtomicUsize = AtomicUsize::new(0);
+
+// Rocket workers kept for the other requests when the stream limit is derived.
+const RESERVED_WORKERS: usize = 4;
+const CLIENT_QUEUE_SIZE: usize = 64;
+const KEEP_ALIVE: u64 = 15; // seconds
+// Streams hold a worker thread, so they are closed and reconnected by clients periodically.
+const MAX_STREAM_DURATION: u64 = 10 * 60; // seconds
+const RETRY_DELAY: u64 = 3000; // milliseconds
+
+
+/// Map layer whose cache changed.
+#[derive(Clone, Copy)]
+pub enum Layer {
+    Reports,
+    FireEvents,
+    ActiveFires,
+    Wind,
+    Weather,
+    Forecast,
+    Shelters,
+    Cctv,
+    DangerPlaces,
+}
+
+impl Layer {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layer::Reports => "reports",
+            Layer::FireEvents => "fire-events",
+            Layer::ActiveFires => "active-fires",
+            Layer::Wind => "wind",
+            Layer::Weather => "weather",
+            Layer::Forecast => "forecast",
+            Layer::Shelters => "shelters",
+            Layer::Cctv => "cctv",
+            Layer::DangerPlaces => "danger-places",
+        }
+    }
+}
+
+
+/// SSE body which blocks until a notification comes.
+pub struct EventStream {
+    rx: Receiver<String>,
+    buffer: Vec<u8>,
+    pos: usize,
+    flushed: bool,
+    started: Instant,
+}
+
+impl EventStream {
+    /// The client slot is reserved by the caller and released on drop.
+    fn new(rx: Receiver<String>) -> Self {
+        EventStream {
+            rx,
+            buffer: format!("retry: {}\n\n", RETRY_DELAY).into_bytes(),
+            pos: 0,
+            flushed: false,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.buffer.len() {
+            let len = buf.len().min(self.buffer.len() - self.pos);
+            buf[..len].copy_from_slice(&self.buffer[self.pos..(self.pos + len)]);
+            self.pos += len;
+
+            return Ok(len);
+        }
+
+        // Ask Rocket to flush the message before waiting the next one.
+        if !self.flushed {
+            self.flushed = true;
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "flush"));
+        }
+
+        if self.started.elapsed() > Duration::new(MAX_STREAM_DURATION, 0) {
+            return Ok(0);
+        }
+
+        self.buffer = match self.rx.recv_timeout(Duration::new(KEEP_ALIVE, 0)) {
+            Ok(msg) => msg.into_bytes(),
+            Err(RecvTimeoutError::Timeout) => b":\n\n".to_vec(),
+            Err(RecvTimeoutError::Disconnected) => return Ok(0),
+        };
+        self.pos = 0;
+        self.flushed = false;
+
+        self.read(buf)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        CLIENT_CNT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+
+/// Set the stream limit from the number of Rocket workers since each stream holds one.
+pub fn init_event_sys(workers: usize) {
+    let max_clients = env::var("EVENTS_MAX_CLIENTS").ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| workers.saturating_sub(RESERVED_WORKERS).max(1));
+
+    if max_clients + RESERVED_WORKERS > workers {
+        warn!("{} event clients leave less than {} of {} workers for other requests",
+            max_clients, RESERVED_WORKERS, workers);
+    }
+
+    info!("Allow {} event clients with {} workers", max_clients, workers);
+
+    MAX_CLIENTS.store(max_clients, Ordering::SeqCst);
+}
+
+#[get("/events")]
+pub fn get_events() -> Result<Content<Stream<EventStream>>, Custom<String>> {
+    // Reserve a slot first so concurrent requests can't pass the limit together.
+    if CLIENT_CNT.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS.load(Ordering::SeqCst) {
+        CLIENT_CNT.fetch_sub(1, Ordering::SeqCst);
+        return Err(Custom(Status::ServiceUnavailable, "Too many event clients".into()));
+    }
+
+    let (tx, rx) = mpsc::sync_channel(CLIENT_QUEUE_SIZE);
+    SUBSCRIBERS.lock().unwrap().push(tx);
+
+    Ok(Content(ContentType::new("text", "event-stream"), Stream::chunked(EventStream::new(rx), 4096)))
+}
+
+pub fn notify(layer: Layer) {
+    let msg = format!("event: {}\ndata: {}\n\n", layer.name(), json!({
+        "layer": layer.name(),
+        "time": Utc::now().timestamp(),
+    }));
+
+    // Drop disconnected clients and skip slow ones.
+    SUBSCRIBERS.lock().unwrap().retain(|tx| {
+        match tx.try_send(msg.clone()) {
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    });
+}
+
+/// Replace the cache and notify clients only if the content changed.
+pub fn update_cache(cache: &RwLock<String>, data: String, layer: Layer) {
+    let changed = {
+        let mut cache = cache.write().unwrap();
+
+        if *cache == data {
+            false
+        }
+        else {
+            *cache = data;
+            true
+        }
+    };
+
+    if changed {
+        notify(layer);
+    }
+}
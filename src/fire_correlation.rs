@@ -0,0 +1,175 @@
+use std::{env, f64};
+
+use crate::fire_sys::FireEvent;
+
+
+lazy_static! {
+    static ref MATCH_DISTANCE: f64 = {
+        env::var("FIRE_MATCH_DISTANCE").ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&dist: &f64| dist > 0.0)
+            .unwrap_or(DEFAULT_MATCH_DISTANCE)
+    };
+    static ref MATCH_HOURS: i64 = {
+        env::var("FIRE_MATCH_HOURS").ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&hours: &i64| hours > 0)
+            .unwrap_or(DEFAULT_MATCH_HOURS)
+    };
+}
+
+const DEFAULT_MATCH_DISTANCE: f64 = 2000.0; // meters
+const DEFAULT_MATCH_HOURS: i64 = 24;
+const EARTH_RADIUS: f64 = 6371008.8; // meters
+
+
+/// Satellite hotspot position and detection time.
+pub struct Hotspot {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub time: i64,
+}
+
+
+/// Index of the nearest event matching each hotspot in distance and time.
+/// Events without a report time are matched by distance only.
+pub fn match_hotspots(hotspots: &[Hotspot], events: &[FireEvent]) -> Vec<Option<usize>> {
+    let max_secs = *MATCH_HOURS * 60 * 60;
+
+    hotspots.iter()
+        .map(|spot| {
+            events.iter()
+                .enumerate()
+                .filter(|(_, evt)| {
+                    evt.reported_time.map_or(true, |time| (spot.time - time).abs() <= max_secs)
+                })
+                .map(|(idx, evt)| {
+                    (idx, distance(spot.latitude, spot.longitude, evt.latitude, evt.longitude))
+                })
+                .filter(|&(_, dist)| dist <= *MATCH_DISTANCE)
+                .fold(None, |acc: Option<(usize, f64)>, (idx, dist)| match acc {
+                    Some((_, min)) if min <= dist => acc,
+                    _ => Some((idx, dist)),
+                })
+                .map(|(idx, _)| idx)
+        })
+        .collect()
+}
+
+/// Number of matched hotspots of each event.
+pub fn count_event_hotspots(matches: &[Option<usize>], event_cnt: usize) -> Vec<usize> {
+    let mut counts = vec![0; event_cnt];
+
+    for idx in matches.iter().filter_map(|&idx| idx) {
+        counts[idx] += 1;
+    }
+
+    counts
+}
+
+
+/// Great-circle distance in meters.
+fn distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value as JsonValue;
+    use crate::fire_sys::{FireEventDetail, FireStatusCode};
+
+    const LATITUDE: f64 = 36.5;
+    const LONGITUDE: f64 = 128.0;
+    const TIME: i64 = 1583033400;
+    // About 1.1km in latitude.
+    const NEAR: f64 = 0.01;
+
+    fn make_event(latitude: f64, longitude: f64, reported_time: Option<i64>) -> FireEvent {
+        let status_code = FireStatusCode::Fighting;
+
+        FireEvent {
+            id: format!("{}-{}", latitude, longitude),
+            status: status_code.status(),
+            status_code,
+            latitude,
+            longitude,
+            address: String::new(),
+            date: String::new(),
+            time: String::new(),
+            reported_time,
+            district: None,
+            detail: FireEventDetail {
+                extinguished_date: None,
+                extinguished_time: None,
+                damage_area: None,
+                cause: None,
+                raw: JsonValue::Null,
+            },
+        }
+    }
+
+    fn make_hotspot(latitude: f64, longitude: f64, time: i64) -> Hotspot {
+        Hotspot { latitude, longitude, time }
+    }
+
+    #[test]
+    fn match_in_distance_only() {
+        let events = vec![make_event(LATITUDE, LONGITUDE, Some(TIME))];
+        let hotspots = vec![
+            make_hotspot(LATITUDE + NEAR, LONGITUDE, TIME),
+            make_hotspot(LATITUDE + 2.0 * NEAR, LONGITUDE, TIME),
+        ];
+
+        assert!(distance(LATITUDE, LONGITUDE, LATITUDE + NEAR, LONGITUDE) < DEFAULT_MATCH_DISTANCE);
+        assert!(distance(LATITUDE, LONGITUDE, LATITUDE + 2.0 * NEAR, LONGITUDE) > DEFAULT_MATCH_DISTANCE);
+        assert_eq!(match_hotspots(&hotspots, &events), vec![Some(0), None]);
+    }
+
+    #[test]
+    fn match_in_time_window_only() {
+        let events = vec![make_event(LATITUDE, LONGITUDE, Some(TIME))];
+        let window = DEFAULT_MATCH_HOURS * 60 * 60;
+        let hotspots = vec![
+            make_hotspot(LATITUDE, LONGITUDE, TIME + window),
+            make_hotspot(LATITUDE, LONGITUDE, TIME - window),
+            make_hotspot(LATITUDE, LONGITUDE, TIME + window + 1),
+            make_hotspot(LATITUDE, LONGITUDE, TIME - window - 1),
+        ];
+
+        assert_eq!(match_hotspots(&hotspots, &events), vec![Some(0), Some(0), None, None]);
+    }
+
+    #[test]
+    fn nearest_event_wins() {
+        let events = vec![
+            make_event(LATITUDE + NEAR, LONGITUDE, Some(TIME)),
+            make_event(LATITUDE + 0.2 * NEAR, LONGITUDE, Some(TIME)),
+            make_event(LATITUDE - 0.5 * NEAR, LONGITUDE, Some(TIME)),
+        ];
+        let hotspots = vec![make_hotspot(LATITUDE, LONGITUDE, TIME)];
+
+        let matches = match_hotspots(&hotspots, &events);
+
+        assert_eq!(matches, vec![Some(1)]);
+        assert_eq!(count_event_hotspots(&matches, events.len()), vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn event_without_report_time_matches_by_distance() {
+        let events = vec![make_event(LATITUDE, LONGITUDE, None)];
+        let hotspots = vec![
+            make_hotspot(LATITUDE, LONGITUDE, TIME + 365 * 24 * 60 * 60),
+            make_hotspot(LATITUDE + 2.0 * NEAR, LONGITUDE, TIME),
+        ];
+
+        assert_eq!(match_hotspots(&hotspots, &events), vec![Some(0), None]);
+    }
+}
@@ -1,14 +1,24 @@
 use std::{
+    env,
     fs,
-    sync::RwLock,
-    time::Duration,
+    sync::{mpsc, RwLock},
+    time::{UNIX_EPOCH, Duration},
+    collections::{BTreeMap, HashMap},
 };
 use rocket::{
-    response::content::Json,
+    response::{
+        content::Json,
+        status::{BadRequest, NotFound},
+    },
 };
 use serde_json::json;
+use chrono::{TimeZone, Utc};
+use threadpool::ThreadPool;
 
+use crate::db;
 use crate::util;
+use crate::district;
+use crate::event_sys::{self, Layer};
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
 
@@ -18,11 +28,38 @@ lazy_static! {
             .map(|text| text.split(',').map(|s| s.to_owned()).collect())
             .expect("Can't initialize district codes")
     };
+    static ref FETCH_CONCURRENCY: usize = {
+        env::var("FORECAST_CONCURRENCY").ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&cnt| cnt > 0)
+            .unwrap_or(DEFAULT_FETCH_CONCURRENCY)
+    };
+    static ref HTTP_CLIENT: reqwest::Client = {
+        let timeout = env::var("FORECAST_TIMEOUT").ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&timeout| timeout > 0)
+            .unwrap_or(DEFAULT_FETCH_TIMEOUT);
+
+        reqwest::Client::builder()
+            .timeout(Duration::new(timeout, 0))
+            .build()
+            .expect("Fail to build HTTP client")
+    };
     static ref FORECAST_DATA: RwLock<String> = {
         RwLock::new(String::new())
     };
+    static ref FORECAST_GEOJSON: RwLock<String> = {
+        RwLock::new(String::new())
+    };
+    static ref FORECAST_CACHE: RwLock<HashMap<String, DistrictForecast>> = {
+        RwLock::new(HashMap::new())
+    };
 }
 
+const DEFAULT_FETCH_CONCURRENCY: usize = 8;
+const DEFAULT_FETCH_TIMEOUT: u64 = 10; // seconds
+const MAX_HISTORY_DAYS: u64 = 90;
+
 
 struct Forecast {
     code: String,
@@ -30,24 +67,23 @@ struct Forecast {
 }
 
 
+/// `level` is `None` until the first successful fetch.
+struct DistrictForecast {
+    level: Option<f32>,
+    fetched_time: Option<i64>,
+    failed: bool,
+}
+
+
 pub fn init_fire_forecast_sys(scheduler: &mut TaskSchedulerBuilder) {
-    let delay = match get_forecast_data(16) {
-        Ok(data) => {
-            update_forecast_cache(data);
-            Duration::new(60 * 30, 0)
-        },
-        Err(err) => {
-            warn!("Fail to init fire forecast cache: {}", err);
-
-            update_forecast_cache(json!({
-                "error": true,
-                "fires": [],
-                "size": 0,
-            }).to_string());
-
-            Duration::new(60 * 1, 0)
-        }
-    };
+    let (success_cnt, fail_cnt) = merge_forecast_data(get_forecast_data(4));
+    update_forecast_cache();
+
+    if success_cnt == 0 {
+        warn!("Fail to init fire forecast cache");
+    }
+
+    let delay = next_forecast_delay(success_cnt, fail_cnt);
 
     scheduler.add_task(Task::new(forecast_job, delay));
 }
@@ -57,31 +93,217 @@ pub fn get_fire_forecast_map() -> Json<String> {
     Json(FORECAST_DATA.read().unwrap().clone())
 }
 
+#[get("/fire-forecast-geojson")]
+pub fn get_fire_forecast_geojson() -> Json<String> {
+    Json(FORECAST_GEOJSON.read().unwrap().clone())
+}
+
+#[get("/fire-forecast?<lat>&<lon>")]
+pub fn get_fire_forecast(lat: f64, lon: f64) -> Result<Json<String>, NotFound<String>> {
+    let district = district::find_district(lat, lon)
+        .ok_or(NotFound("There is no district at that point".into()))?;
+
+    let cache = FORECAST_CACHE.read().unwrap();
+
+    cache.get(&district.code)
+        .ok_or(NotFound("There is no forecast for the district".into()))
+        .map(|forecast| {
+            Json(json!({
+                "code": district.code,
+                "name": district.name,
+                "lvl": forecast.level,
+                "time": forecast.fetched_time,
+                "failed": forecast.failed,
+            }).to_string())
+        })
+}
+
+#[get("/fire-forecast-history?<code>&<days>")]
+pub fn get_fire_forecast_history(code: String, days: Option<u64>)
+    -> Result<Json<String>, BadRequest<String>> {
+
+    let days = days.unwrap_or(7).min(MAX_HISTORY_DAYS);
+    let since = util::current_time()
+        - Duration::new(days * 24 * 60 * 60, 0);
+
+    let history = db::get_forecast_history(&code, since)
+        .map_err(|err| BadRequest(Some(err.to_string())))?;
+
+    let kst = util::kst();
+    let mut daily_max = BTreeMap::new();
+
+    let part_history = history.iter()
+        .map(|h| {
+            let time = util::to_secs(h.fetched_time);
+
+            // Daily maxima are grouped by the local(KST) date.
+            let date = kst.timestamp(time as i64, 0).format("%Y-%m-%d").to_string();
+            let max_lvl = daily_max.entry(date).or_insert(h.lvl);
+            if h.lvl > *max_lvl {
+                *max_lvl = h.lvl;
+            }
+
+            json!({
+                "lvl": h.lvl,
+                "time": time,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let part_daily_max = daily_max.into_iter()
+        .map(|(date, lvl)| {
+            json!({
+                "date": date,
+                "lvl": lvl,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "code": code,
+        "name": district::get_district(&code).map(|d| &d.name),
+        "history": part_history,
+        "daily_max": part_daily_max,
+    }).to_string()))
+}
+
+
+pub fn get_forecast_level(code: &str) -> Option<f32> {
+    FORECAST_CACHE.read().unwrap().get(code).and_then(|forecast| forecast.level)
+}
+
 
 fn forecast_job() -> Duration {
     info!("Start job");
 
-    match get_forecast_data(8) {
-        Ok(data) => {
-            update_forecast_cache(data);
-            Duration::new(60 * 30, 0)
-        },
-        Err(err) => {
-            warn!("Fail to get fire forecast data: {}", err);
-            Duration::new(60 * 1, 0)
-        },
+    let (success_cnt, fail_cnt) = merge_forecast_data(get_forecast_data(2));
+    update_forecast_cache();
+
+    if fail_cnt > 0 {
+        warn!("Fail to get fire forecast data of {} districts", fail_cnt);
+    }
+
+    next_forecast_delay(success_cnt, fail_cnt)
+}
+
+fn next_forecast_delay(success_cnt: usize, fail_cnt: usize) -> Duration {
+    if success_cnt == 0 {
+        Duration::new(60 * 1, 0)
+    }
+    else if fail_cnt > 0 {
+        Duration::new(60 * 5, 0)
+    }
+    else {
+        Duration::new(60 * 30, 0)
     }
 }
 
-fn update_forecast_cache(json: String) {
-    *FORECAST_DATA.write().unwrap() = json;
+fn merge_forecast_data(results: Vec<(String, Result<Forecast, String>)>) -> (usize, usize) {
+    let fetched_time = Utc::now().timestamp();
+
+    let mut success_cnt = 0;
+    let mut fail_cnt = 0;
+    let mut history = Vec::new();
+
+    let mut cache = FORECAST_CACHE.write().unwrap();
+
+    for (code, result) in results {
+        match result {
+            Ok(forecast) => {
+                history.push(db::models::NewForecastHistory {
+                    code: forecast.code.clone(),
+                    lvl: forecast.level,
+                    fetched_time: UNIX_EPOCH + Duration::new(fetched_time as u64, 0),
+                });
+                cache.insert(forecast.code, DistrictForecast {
+                    level: Some(forecast.level),
+                    fetched_time: Some(fetched_time),
+                    failed: false,
+                });
+                success_cnt += 1;
+            },
+            Err(err) => {
+                // Keep the previous level and just mark it as stale.
+                cache.entry(code.clone())
+                    .or_insert(DistrictForecast {
+                        level: None,
+                        fetched_time: None,
+                        failed: true,
+                    })
+                    .failed = true;
+                warn!("Fail to get {} forecast data: {}", code, err);
+                fail_cnt += 1;
+            },
+        }
+    }
+
+    drop(cache);
+
+    if !history.is_empty() {
+        if let Err(err) = db::insert_forecast_history(&history) {
+            warn!("Fail to save fire forecast history: {}", err);
+        }
+    }
+
+    (success_cnt, fail_cnt)
+}
+
+fn update_forecast_cache() {
+    let cache = FORECAST_CACHE.read().unwrap();
+
+    // Follow the order of the district codes so the output is stable.
+    let part_forecasts = DISTRICT_CODES.iter()
+        .filter_map(|code| cache.get(code).map(|forecast| (code, forecast)))
+        .map(|(code, forecast)| {
+            json!({
+                "code": code,
+                "lvl": forecast.level,
+                "time": forecast.fetched_time,
+                "failed": forecast.failed,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let json = json!({
+        "error": cache.values().all(|forecast| forecast.level.is_none()),
+        "forecasts": part_forecasts,
+        "size": part_forecasts.len(),
+    }).to_string();
+
+    let features = DISTRICT_CODES.iter()
+        .filter_map(|code| district::get_district(code))
+        .map(|d| {
+            let forecast = cache.get(&d.code);
+
+            json!({
+                "type": "Feature",
+                "geometry": d.geometry,
+                "properties": {
+                    "code": d.code,
+                    "name": d.name,
+                    "lvl": forecast.and_then(|f| f.level),
+                    "time": forecast.and_then(|f| f.fetched_time),
+                    "failed": forecast.map(|f| f.failed),
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let geojson = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    }).to_string();
+
+    *FORECAST_GEOJSON.write().unwrap() = geojson;
+    event_sys::update_cache(&FORECAST_DATA, json, Layer::Forecast);
 }
 
 fn get_forecast_by_code(code: &str) -> Result<Forecast, String> {
     let uri = format!("http://forestfire.nifos.go.kr/mobile/jsp/fireGrade.jsp?cd={}&subCd={}",
         &code[..2], code);
 
-    reqwest::get(&uri)
+    HTTP_CLIENT.get(&uri)
+        .send()
         .and_then(|mut res| res.text())
         .map_err(|err| err.to_string())
         .and_then(|html| {
@@ -100,42 +322,31 @@ fn get_forecast_by_code(code: &str) -> Result<Forecast, String> {
         })
 }
 
-fn get_forecast_data(retry_cnt: usize) -> Result<String, String> {
-    let mut left_retries = retry_cnt;
-    let mut total_forecasts = Vec::new();
+fn get_forecast_data(retry_cnt: usize) -> Vec<(String, Result<Forecast, String>)> {
+    let pool = ThreadPool::new(*FETCH_CONCURRENCY);
+    let (tx, rx) = mpsc::channel();
 
-    for code in &*DISTRICT_CODES {
-        loop {
-            match get_forecast_by_code(code) {
-                Ok(forecast) => {
-                    total_forecasts.push(forecast);
+    for code in DISTRICT_CODES.iter() {
+        let tx = tx.clone();
+
+        pool.execute(move || {
+            let mut result = get_forecast_by_code(code);
+
+            for retry in 1..=retry_cnt {
+                if result.is_ok() {
                     break;
-                },
-                Err(err) => {
-                    if left_retries > 0 {
-                        warn!("Retry({}/{}) to get {} forecast data", left_retries, retry_cnt, code);
-                        left_retries -= 1;
-                    }
-                    else {
-                        return Err(err);
-                    }
-                },
+                }
+
+                warn!("Retry({}/{}) to get {} forecast data", retry, retry_cnt, code);
+                result = get_forecast_by_code(code);
             }
-        }
+
+            let _ = tx.send((code.clone(), result));
+        });
     }
 
-    let part_forecasts = total_forecasts.into_iter()
-        .map(|forecast| {
-            json!({
-                "code": forecast.code,
-                "lvl": forecast.level,
-            })
-        })
-        .collect::<Vec<_>>();
+    // Close our sender so the receiver ends when all jobs are done.
+    drop(tx);
 
-    Ok(json!({
-        "error": false,
-        "forecasts": part_forecasts,
-        "size": part_forecasts.len(),
-    }).to_string())
+    rx.iter().collect()
 }
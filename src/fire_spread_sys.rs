@@ -0,0 +1,156 @@
+use std::f64;
+
+use rocket::{
+    response::{
+        content::Json,
+        status::NotFound,
+    },
+};
+use serde_json::json;
+use cgmath::Vector2;
+
+use crate::wind_sys;
+use crate::fire_sys::{self, FireStatus};
+use crate::fire_forecast_sys;
+
+
+const DEFAULT_SPREAD_HOURS: u32 = 3;
+const MAX_SPREAD_HOURS: u32 = 12;
+const DEFAULT_FORECAST_LEVEL: f32 = 50.0;
+const ELLIPSE_POINTS: usize = 36;
+const METERS_PER_DEGREE: f64 = 111_320.0;
+const MPS_TO_MPH: f64 = 2.237;
+const MAX_LENGTH_TO_BREADTH: f64 = 8.0;
+
+
+/// Elliptical spread of an hour from the ignition point.
+struct SpreadEllipse {
+    hours: u32,
+    head: f64, // meters
+    back: f64, // meters
+    flank: f64, // meters
+}
+
+
+/// Likely spread area of an active fire event for each of the next `hours` hours.
+#[get("/fire-spread?<event>&<hours>")]
+pub fn get_fire_spread(event: String, hours: Option<u32>) -> Result<Json<String>, NotFound<String>> {
+    let hours = hours.unwrap_or(DEFAULT_SPREAD_HOURS).max(1).min(MAX_SPREAD_HOURS);
+
+    let event = fire_sys::get_fire_event(&event)
+        .ok_or(NotFound("There is no such fire event".into()))?;
+
+    if event.status != FireStatus::Fire {
+        return Err(NotFound("The fire is not active".into()));
+    }
+
+    let wind = wind_sys::get_wind_at(event.latitude, event.longitude);
+    let level = event.district
+        .and_then(|d| fire_forecast_sys::get_forecast_level(&d.code));
+
+    // The wind vector points where the wind comes from, and the fire goes the other way.
+    let spread_dir = wind.map(|(wind, _)| -wind).unwrap_or(Vector2::new(0.0, 0.0));
+    let wind_speed = spread_dir.x.hypot(spread_dir.y);
+
+    let features = (1..=hours)
+        .map(|h| {
+            let ellipse = make_spread_ellipse(h, wind_speed, level.unwrap_or(DEFAULT_FORECAST_LEVEL));
+            let ring = make_ellipse_ring(&ellipse, event.latitude, event.longitude, spread_dir);
+
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [ring],
+                },
+                "properties": {
+                    "hours": ellipse.hours,
+                    "head": ellipse.head,
+                    "back": ellipse.back,
+                    "flank": ellipse.flank,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let direction = spread_dir.x.atan2(spread_dir.y).to_degrees();
+
+    Ok(Json(json!({
+        "type": "FeatureCollection",
+        "features": features,
+        "event": event.id,
+        "latitude": event.latitude,
+        "longitude": event.longitude,
+        "wind_speed": wind.map(|_| wind_speed),
+        "spread_dir": wind.map(|_| if direction < 0.0 { direction + 360.0 } else { direction }),
+        "wind_time": wind.map(|(_, time)| time),
+        "lvl": level,
+    }).to_string()))
+}
+
+
+/// Head rate of spread in m/min.
+/// The base rate grows with the forecast level and the wind multiplies it.
+fn head_rate_of_spread(wind_speed: f64, level: f32) -> f64 {
+    let base = 0.5 + 2.0 * (level as f64 / 100.0).max(0.0).min(1.0);
+    base * (1.0 + 0.3 * wind_speed.powf(1.5))
+}
+
+/// Length-to-breadth ratio of the spread ellipse from Anderson(1983).
+fn length_to_breadth(wind_speed: f64) -> f64 {
+    let mph = wind_speed * MPS_TO_MPH;
+    let ratio = 0.936 * (0.2566 * mph).exp() + 0.461 * (-0.1548 * mph).exp() - 0.397;
+
+    ratio.max(1.0).min(MAX_LENGTH_TO_BREADTH)
+}
+
+fn make_spread_ellipse(hours: u32, wind_speed: f64, level: f32) -> SpreadEllipse {
+    let lb = length_to_breadth(wind_speed);
+    let eccentricity = (1.0 - 1.0 / (lb * lb)).sqrt();
+
+    let head = head_rate_of_spread(wind_speed, level) * 60.0 * hours as f64;
+    let back = head * (1.0 - eccentricity) / (1.0 + eccentricity);
+
+    SpreadEllipse {
+        hours,
+        head,
+        back,
+        flank: (head + back) / 2.0 / lb,
+    }
+}
+
+/// Closed ring of `[lon, lat]` around the ellipse with the head toward `direction`.
+fn make_ellipse_ring(ellipse: &SpreadEllipse, latitude: f64, longitude: f64, direction: Vector2<f64>)
+    -> Vec<[f64; 2]> {
+
+    let length = direction.x.hypot(direction.y);
+    let (dir_x, dir_y) = if length > f64::EPSILON {
+        (direction.x / length, direction.y / length)
+    }
+    else {
+        (0.0, 1.0)
+    };
+
+    let semi_major = (ellipse.head + ellipse.back) / 2.0;
+    let center = (ellipse.head - ellipse.back) / 2.0;
+    let lon_scale = METERS_PER_DEGREE * latitude.to_radians().cos();
+
+    let mut ring = (0..ELLIPSE_POINTS)
+        .map(|i| {
+            let angle = 2.0 * f64::consts::PI * i as f64 / ELLIPSE_POINTS as f64;
+
+            // Along and across the spread direction in meters.
+            let along = center + semi_major * angle.cos();
+            let across = ellipse.flank * angle.sin();
+
+            let x = along * dir_x + across * dir_y;
+            let y = along * dir_y - across * dir_x;
+
+            [longitude + x / lon_scale, latitude + y / METERS_PER_DEGREE]
+        })
+        .collect::<Vec<_>>();
+
+    ring.push(ring[0]);
+
+    ring
+}
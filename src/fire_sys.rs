@@ -1,12 +1,24 @@
 use std::{
     sync::RwLock,
-    time::Duration,
+    time::{UNIX_EPOCH, Duration},
+    collections::{HashMap, HashSet},
 };
 use rocket::{
-    response::content::Json,
+    http::Status,
+    response::{
+        content::Json,
+        status::{BadRequest, Custom},
+    },
 };
 use serde_json::{Value as JsonValue, json};
+use chrono::{TimeZone, Utc};
 
+use crate::db;
+use crate::util;
+use crate::district::{self, District};
+use crate::event_sys::{self, Layer};
+use crate::active_fire_sys;
+use crate::fire_correlation;
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
 
@@ -14,29 +26,145 @@ lazy_static! {
     static ref FIRE_EVENT_MAP: RwLock<String> = {
         RwLock::new(String::new())
     };
+    static ref FIRE_EVENTS: RwLock<Vec<FireEvent>> = {
+        RwLock::new(Vec::new())
+    };
+    /// Hotspot count of each current event from the last correlation.
+    static ref EVENT_HOTSPOTS: RwLock<HashMap<String, usize>> = {
+        RwLock::new(HashMap::new())
+    };
 }
 
+const MAX_HISTORY_DAYS: u64 = 90;
+const MAX_CHANGES: i64 = 500;
+
 
-enum FireStatus {
+/// Map status of an event.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FireStatus {
     Fire,
     Extinguished,
     Clear,
 }
 
 
+/// Progress code(`frfrPrgrsStcd`) of upstream.
+#[derive(Clone, PartialEq)]
+pub enum FireStatusCode {
+    Reported,
+    Fighting,
+    Extinguished,
+    Monitoring,
+    NotFire,
+    Unknown(String),
+}
+
+impl FireStatusCode {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "01" => FireStatusCode::Reported,
+            "02" => FireStatusCode::Fighting,
+            "03" => FireStatusCode::Extinguished,
+            "04" => FireStatusCode::Monitoring,
+            "05" => FireStatusCode::NotFire,
+            _ => FireStatusCode::Unknown(code.to_owned()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            FireStatusCode::Reported => "01",
+            FireStatusCode::Fighting => "02",
+            FireStatusCode::Extinguished => "03",
+            FireStatusCode::Monitoring => "04",
+            FireStatusCode::NotFire => "05",
+            FireStatusCode::Unknown(code) => code,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FireStatusCode::Reported => "접수",
+            FireStatusCode::Fighting => "진화중",
+            FireStatusCode::Extinguished => "진화완료",
+            FireStatusCode::Monitoring => "뒷불감시",
+            FireStatusCode::NotFire => "산불외종료",
+            FireStatusCode::Unknown(_) => "알수없음",
+        }
+    }
+
+    pub fn status(&self) -> FireStatus {
+        match self {
+            FireStatusCode::Reported | FireStatusCode::Fighting => FireStatus::Fire,
+            FireStatusCode::NotFire => FireStatus::Clear,
+            _ => FireStatus::Extinguished,
+        }
+    }
+}
+
+
+/// Optional upstream fields. `raw` keeps the whole record so nothing is lost.
+#[derive(Clone)]
+pub struct FireEventDetail {
+    pub extinguished_date: Option<String>,
+    pub extinguished_time: Option<String>,
+    pub damage_area: Option<f64>, // ha
+    pub cause: Option<String>,
+    pub raw: JsonValue,
+}
+
+impl FireEventDetail {
+    fn new(evt: &JsonValue) -> Self {
+        let text = |keys: &[&str]| {
+            keys.iter()
+                .filter_map(|&key| evt[key].as_str())
+                .map(|s| s.trim())
+                .find(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+        };
+
+        FireEventDetail {
+            extinguished_date: text(&["frfrPotfrEndDt", "potfrEndDt"]),
+            extinguished_time: text(&["frfrPotfrEndHms", "potfrEndHms"]),
+            damage_area: text(&["frfrDmgArea", "dmgArea"]).and_then(|area| area.parse().ok()),
+            cause: text(&["frfrOccrCause", "occrCause"]),
+            raw: evt.clone(),
+        }
+    }
+}
+
+
+#[derive(Clone)]
+pub struct FireEvent {
+    pub id: String,
+    pub status: FireStatus,
+    pub status_code: FireStatusCode,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub address: String,
+    pub date: String,
+    pub time: String,
+    pub reported_time: Option<i64>,
+    pub district: Option<&'static District>,
+    pub detail: FireEventDetail,
+}
+
+
 pub fn init_fire_sys(scheduler: &mut TaskSchedulerBuilder) {
-    let delay = match get_fire_event_json() {
-        Ok(data) => {
-            update_fire_event_map(data);
+    let delay = match get_fire_events() {
+        Ok(events) => {
+            if let Some(prev) = previous_statuses() {
+                save_fire_event_changes(&prev, &events);
+            }
+
+            save_fire_events(&events);
+            update_fire_event_map(events);
             Duration::new(60 * 3, 0)
         },
         Err(err) => {
             warn!("Fail to init fire events: {}", err);
 
-            update_fire_event_map(json!({
-                "events": [],
-                "size": 0,
-            }).to_string());
+            update_fire_event_map(Vec::new());
 
             Duration::new(60 * 1, 0)
         },
@@ -50,12 +178,204 @@ pub fn get_fire_event_map() -> Json<String> {
     Json(FIRE_EVENT_MAP.read().unwrap().clone())
 }
 
+/// Events seen in the last `days` days including the ones dropped by upstream.
+#[get("/fire-event-history?<days>&<district>")]
+pub fn get_fire_event_history(days: Option<u64>, district: Option<String>)
+    -> Result<Json<String>, BadRequest<String>> {
+
+    let days = days.unwrap_or(7).min(MAX_HISTORY_DAYS);
+    let since = util::current_time()
+        - Duration::new(days * 24 * 60 * 60, 0);
+
+    let events = db::get_fire_events_since(since, district.as_ref().map(|d| d.as_str()))
+        .map_err(|err| BadRequest(Some(err.to_string())))?;
+
+    let part_events = events.iter()
+        .map(|evt| {
+            let status_code = FireStatusCode::from_code(&evt.status_code);
+
+            json!({
+                "id": evt.id,
+                "status": status_code.status() as i32,
+                "status_code": status_code.code(),
+                "status_label": status_code.label(),
+                "latitude": evt.latitude,
+                "longitude": evt.longitude,
+                "address": evt.address,
+                "date": evt.reported_date,
+                "time": evt.reported_time,
+                "district": evt.district,
+                "district_name": evt.district.as_ref()
+                    .and_then(|code| district::get_district(code))
+                    .map(|d| &d.name),
+                "first_seen": util::to_secs(evt.first_seen),
+                "last_seen": util::to_secs(evt.last_seen),
+                "active": evt.active,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "events": part_events,
+        "size": part_events.len(),
+    }).to_string()))
+}
+
+/// New fires, status changes and disappearances in order.
+/// The first page starts after `since` and the next ones after the `cursor` of the previous page.
+#[get("/fire-events/changes?<since>&<after>")]
+pub fn get_fire_event_changes(since: Option<u64>, after: Option<i32>) -> Result<Json<String>, BadRequest<String>> {
+    let since = since.unwrap_or_else(|| Utc::now().timestamp() as u64 - 24 * 60 * 60);
+    let since_time = UNIX_EPOCH.checked_add(Duration::new(since, 0))
+        .ok_or(BadRequest(Some("Invalid since".into())))?;
+
+    let changes = db::get_fire_event_changes_after(since_time, after, MAX_CHANGES)
+        .map_err(|err| BadRequest(Some(err.to_string())))?;
+
+    let part_changes = changes.iter()
+        .map(|change| {
+            json!({
+                "event": change.event_id,
+                "kind": change.kind,
+                "old_status": change.old_code.as_ref().map(|code| FireStatusCode::from_code(code).status() as i32),
+                "new_status": change.new_code.as_ref().map(|code| FireStatusCode::from_code(code).status() as i32),
+                "old_code": change.old_code,
+                "new_code": change.new_code,
+                "time": util::to_secs(change.changed_time),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let latest = changes.last()
+        .map(|change| util::to_secs(change.changed_time))
+        .unwrap_or(since);
+
+    // Clients pass this as the next `after`. Ids keep changes of the same time apart.
+    let cursor = changes.last()
+        .map(|change| change.id)
+        .or(after);
+
+    Ok(Json(json!({
+        "changes": part_changes,
+        "size": part_changes.len(),
+        "latest": latest,
+        "cursor": cursor,
+        "more": changes.len() as i64 >= MAX_CHANGES,
+    }).to_string()))
+}
+
+/// Details of a current event, or the saved one if upstream dropped it.
+/// Both have the same fields and the ones which aren't known are null.
+#[get("/fire-event?<id>")]
+pub fn get_fire_event_detail(id: String) -> Result<Json<String>, Custom<String>> {
+    if let Some(evt) = get_fire_event(&id) {
+        let hotspot_cnt = EVENT_HOTSPOTS.read().unwrap().get(&evt.id).cloned().unwrap_or(0);
+
+        // Only the saved event knows when it was seen.
+        let saved = db::get_fire_event(&id).unwrap_or_else(|err| {
+            warn!("Fail to get saved fire event: {}", err);
+            None
+        });
+
+        return Ok(Json(json!({
+            "id": evt.id,
+            "active": true,
+            "status": evt.status as i32,
+            "status_code": evt.status_code.code(),
+            "status_label": evt.status_code.label(),
+            "latitude": evt.latitude,
+            "longitude": evt.longitude,
+            "address": evt.address,
+            "date": evt.date,
+            "time": evt.time,
+            "reported_time": evt.reported_time,
+            "district": evt.district.map(|d| &d.code),
+            "district_name": evt.district.map(|d| &d.name),
+            "extinguished_date": evt.detail.extinguished_date,
+            "extinguished_time": evt.detail.extinguished_time,
+            "damage_area": evt.detail.damage_area,
+            "cause": evt.detail.cause,
+            "hotspots": hotspot_cnt,
+            "satellite_confirmed": hotspot_cnt > 0,
+            "first_seen": saved.as_ref().map(|saved| util::to_secs(saved.first_seen)),
+            "last_seen": saved.as_ref().map(|saved| util::to_secs(saved.last_seen)),
+            "raw": evt.detail.raw,
+        }).to_string()));
+    }
+
+    let evt = db::get_fire_event(&id)
+        .map_err(|err| Custom(Status::BadRequest, err.to_string()))?
+        .ok_or(Custom(Status::NotFound, "There is no such fire event".into()))?;
+
+    let status_code = FireStatusCode::from_code(&evt.status_code);
+
+    // Hotspots are only matched with the current events.
+    Ok(Json(json!({
+        "id": evt.id,
+        "active": evt.active,
+        "status": status_code.status() as i32,
+        "status_code": status_code.code(),
+        "status_label": status_code.label(),
+        "latitude": evt.latitude,
+        "longitude": evt.longitude,
+        "address": evt.address,
+        "date": evt.reported_date,
+        "time": evt.reported_time,
+        "reported_time": parse_reported_time(&evt.reported_date, &evt.reported_time),
+        "district": evt.district,
+        "district_name": evt.district.as_ref()
+            .and_then(|code| district::get_district(code))
+            .map(|d| &d.name),
+        "extinguished_date": evt.extinguished_date,
+        "extinguished_time": evt.extinguished_time,
+        "damage_area": evt.damage_area,
+        "cause": evt.cause,
+        "hotspots": JsonValue::Null,
+        "satellite_confirmed": JsonValue::Null,
+        "first_seen": util::to_secs(evt.first_seen),
+        "last_seen": util::to_secs(evt.last_seen),
+        "raw": JsonValue::Null,
+    }).to_string()))
+}
+
+pub fn current_fire_events() -> Vec<FireEvent> {
+    FIRE_EVENTS.read().unwrap().clone()
+}
+
+/// Rebuild the map with the current satellite hotspots.
+pub fn refresh_fire_event_map() {
+    let events = current_fire_events();
+    let hotspots = active_fire_sys::current_hotspots();
+
+    let matches = fire_correlation::match_hotspots(&hotspots, &events);
+    let counts = fire_correlation::count_event_hotspots(&matches, events.len());
+
+    *EVENT_HOTSPOTS.write().unwrap() = events.iter()
+        .map(|evt| evt.id.clone())
+        .zip(counts.iter().cloned())
+        .collect();
+
+    event_sys::update_cache(&FIRE_EVENT_MAP, make_fire_event_json(&events, &counts), Layer::FireEvents);
+}
+
+pub fn get_fire_event(id: &str) -> Option<FireEvent> {
+    FIRE_EVENTS.read().unwrap().iter()
+        .find(|evt| evt.id == id)
+        .cloned()
+}
+
+
 fn fire_event_job() -> Duration {
     info!("Start job for fire event");
 
-    match get_fire_event_json() {
-        Ok(data) => {
-            update_fire_event_map(data);
+    match get_fire_events() {
+        Ok(events) => {
+            if let Some(prev) = previous_statuses() {
+                save_fire_event_changes(&prev, &events);
+            }
+
+            save_fire_events(&events);
+            update_fire_event_map(events);
             Duration::new(60 * 3, 0)
         },
         Err(err) => {
@@ -65,88 +385,346 @@ fn fire_event_job() -> Duration {
     }
 }
 
-fn update_fire_event_map(data: String) {
-    *FIRE_EVENT_MAP.write().unwrap() = data;
+fn update_fire_event_map(events: Vec<FireEvent>) {
+    *FIRE_EVENTS.write().unwrap() = events;
+
+    refresh_fire_event_map();
+    active_fire_sys::refresh_fire_map();
+}
+
+/// Status codes to compare the new events with.
+/// Use the events saved before if there is nothing in memory like after a restart or a failed first load.
+fn previous_statuses() -> Option<HashMap<String, String>> {
+    let events = FIRE_EVENTS.read().unwrap();
+
+    if !events.is_empty() {
+        return Some(events.iter()
+            .map(|evt| (evt.id.clone(), evt.status_code.code().to_owned()))
+            .collect());
+    }
+
+    drop(events);
+
+    match db::get_active_fire_events() {
+        Ok(saved) => Some(saved.into_iter()
+            .map(|evt| (evt.id, evt.status_code))
+            .collect()),
+        Err(err) => {
+            warn!("Fail to load saved fire events: {}", err);
+            None
+        },
+    }
+}
+
+/// Save transitions from `prev`, which maps event ids to status codes.
+/// Codes are compared so a change like 접수 to 진화중 is kept though both are on fire.
+fn save_fire_event_changes(prev: &HashMap<String, String>, events: &[FireEvent]) {
+    let changed_time = util::current_time();
+    let change = |event_id: &str, kind: &str, old_code: Option<&str>, new_code: Option<&str>| {
+        db::models::NewFireEventChange {
+            event_id: event_id.to_owned(),
+            kind: kind.to_owned(),
+            old_code: old_code.map(|code| code.to_owned()),
+            new_code: new_code.map(|code| code.to_owned()),
+            changed_time,
+        }
+    };
+
+    let mut changes = Vec::new();
+
+    for evt in events {
+        let code = evt.status_code.code();
+
+        match prev.get(&evt.id) {
+            None => changes.push(change(&evt.id, "new", None, Some(code))),
+            Some(old) if old != code => changes.push(change(&evt.id, "status", Some(old), Some(code))),
+            _ => (),
+        }
+    }
+
+    let mut gone = prev.iter()
+        .filter(|(id, _)| !events.iter().any(|evt| &evt.id == *id))
+        .collect::<Vec<_>>();
+    gone.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (id, old) in gone {
+        changes.push(change(id, "gone", Some(old), None));
+    }
+
+    if changes.is_empty() {
+        return;
+    }
+
+    info!("{} fire event changes", changes.len());
+
+    if let Err(err) = db::insert_fire_event_changes(&changes) {
+        warn!("Fail to save fire event changes: {}", err);
+    }
+}
+
+fn save_fire_events(events: &[FireEvent]) {
+    let seen_time = util::current_time();
+
+    let records = events.iter()
+        .map(|evt| db::models::NewFireEvent {
+            id: evt.id.clone(),
+            status_code: evt.status_code.code().to_owned(),
+            latitude: evt.latitude,
+            longitude: evt.longitude,
+            address: evt.address.clone(),
+            district: evt.district.map(|d| d.code.clone()),
+            reported_date: evt.date.clone(),
+            reported_time: evt.time.clone(),
+            extinguished_date: evt.detail.extinguished_date.clone(),
+            extinguished_time: evt.detail.extinguished_time.clone(),
+            damage_area: evt.detail.damage_area,
+            cause: evt.detail.cause.clone(),
+            first_seen: seen_time,
+            last_seen: seen_time,
+            active: true,
+        })
+        .collect::<Vec<_>>();
+
+    if let Err(err) = db::upsert_fire_events(&records, seen_time) {
+        warn!("Fail to save fire events: {}", err);
+    }
 }
 
-fn get_fire_event_json() -> Result<String, String> {
+fn get_fire_events() -> Result<Vec<FireEvent>, String> {
     let json_result = reqwest::get("http://116.67.84.152/ffas/gis/selectFireShowList.do")
         .and_then(|mut res| res.text());
 
     match json_result {
-        Ok(json_str) => {
-            serde_json::from_str::<JsonValue>(&json_str)
-                .map_err(|err| err.to_string())
-                .and_then(|v| {
-                    v.as_array()
-                        .and_then(|arr| arr.get(0))
-                        .and_then(|arr| arr.as_array())
-                        .ok_or("Invalid fire event data".into())
-                        .map(|events| {
-                            // Parse each fire events.
-                            let results = events.into_iter().map(|evt| {
-                                let status_opt = evt["frfrPrgrsStcd"].as_str()
-                                    .map(|s| convert_str_to_fire_status(s));
-                                let latitude_opt = evt["frfrSttmnLctnYcrd"].as_str()
-                                    .filter(|y| y.find('.').is_some())
-                                    .and_then(|y| y.parse::<f64>().ok());
-                                let longitude_opt = evt["frfrSttmnLctnXcrd"].as_str()
-                                    .filter(|x| x.find('.').is_some())
-                                    .and_then(|x| x.parse::<f64>().ok());
-                                let address_opt = evt["frfrSttmnAddr"].as_str()
-                                    .map(|adr| adr.to_owned());
-                                let date_opt = evt["frfrSttmnDt"].as_str()
-                                    .map(|date| date.to_owned());
-                                let time_opt = evt["frfrSttmnHms"].as_str()
-                                    .map(|time| time.to_owned());
-
-                                if let
-                                    (Some(status),
-                                    Some(latitude),
-                                    Some(longitude),
-                                    Some(address),
-                                    Some(date),
-                                    Some(time)) =
-                                        (status_opt,
-                                        latitude_opt,
-                                        longitude_opt,
-                                        address_opt,
-                                        date_opt,
-                                        time_opt)
-                                {
-                                    Ok(json!({
-                                        "status": status as i32,
-                                        "latitude": latitude,
-                                        "longitude": longitude,
-                                        "address": address,
-                                        "date": date,
-                                        "time": time,
-                                    }))
-                                }
-                                else {
-                                    Err("Fail to parse fire events".to_owned())
-                                }
-                            });
-
-                            let fire_events = results
-                                .filter_map(|res| res.ok())
-                                .collect::<Vec<_>>();
-
-                            json!({
-                                "events": fire_events,
-                                "size": fire_events.len(),
-                            }).to_string()
-                        })
-                })
-        },
+        Ok(json_str) => parse_fire_events(&json_str),
         Err(err) => Err(err.to_string()),
     }
 }
 
-fn convert_str_to_fire_status(status: &str) -> FireStatus {
-    match status {
-        "01" | "02" => FireStatus::Fire,
-        "05" => FireStatus::Clear,
-        _ => FireStatus::Extinguished,
+/// Parse the upstream event list, keeping the first of the events with the same id.
+fn parse_fire_events(json_str: &str) -> Result<Vec<FireEvent>, String> {
+    serde_json::from_str::<JsonValue>(json_str)
+        .map_err(|err| err.to_string())
+        .and_then(|v| {
+            v.as_array()
+                .and_then(|arr| arr.get(0))
+                .and_then(|arr| arr.as_array())
+                .ok_or("Invalid fire event data".into())
+                .map(|events| {
+                    // Parse each fire events.
+                    let results = events.into_iter().map(|evt| {
+                        let status_opt = evt["frfrPrgrsStcd"].as_str()
+                            .map(|s| FireStatusCode::from_code(s));
+                        let latitude_opt = evt["frfrSttmnLctnYcrd"].as_str()
+                            .filter(|y| y.find('.').is_some())
+                            .and_then(|y| y.parse::<f64>().ok());
+                        let longitude_opt = evt["frfrSttmnLctnXcrd"].as_str()
+                            .filter(|x| x.find('.').is_some())
+                            .and_then(|x| x.parse::<f64>().ok());
+                        let address_opt = evt["frfrSttmnAddr"].as_str()
+                            .map(|adr| adr.to_owned());
+                        let date_opt = evt["frfrSttmnDt"].as_str()
+                            .map(|date| date.to_owned());
+                        let time_opt = evt["frfrSttmnHms"].as_str()
+                            .map(|time| time.to_owned());
+
+                        if let
+                            (Some(status),
+                            Some(latitude),
+                            Some(longitude),
+                            Some(address),
+                            Some(date),
+                            Some(time)) =
+                                (status_opt,
+                                latitude_opt,
+                                longitude_opt,
+                                address_opt,
+                                date_opt,
+                                time_opt)
+                        {
+                            Ok(FireEvent {
+                                id: make_event_id(&date, &time, latitude, longitude),
+                                reported_time: parse_reported_time(&date, &time),
+                                status: status.status(),
+                                status_code: status,
+                                latitude,
+                                longitude,
+                                address,
+                                date,
+                                time,
+                                district: district::find_district(latitude, longitude),
+                                detail: FireEventDetail::new(evt),
+                            })
+                        }
+                        else {
+                            Err("Fail to parse fire events".to_owned())
+                        }
+                    });
+
+                    // Upstream may list an event twice, which can't be upserted in one statement.
+                    let mut ids = HashSet::new();
+                    results
+                        .filter_map(|res| res.ok())
+                        .filter(|evt| ids.insert(evt.id.clone()))
+                        .collect::<Vec<_>>()
+                })
+        })
+}
+
+fn make_fire_event_json(fire_events: &[FireEvent], hotspot_counts: &[usize]) -> String {
+    let part_events = fire_events.iter().zip(hotspot_counts)
+        .map(|(evt, &hotspots)| {
+            json!({
+                "id": evt.id,
+                "status": evt.status as i32,
+                "status_code": evt.status_code.code(),
+                "status_label": evt.status_code.label(),
+                "latitude": evt.latitude,
+                "longitude": evt.longitude,
+                "address": evt.address,
+                "date": evt.date,
+                "time": evt.time,
+                "district": evt.district.map(|d| &d.code),
+                "district_name": evt.district.map(|d| &d.name),
+                "hotspots": hotspots,
+                "satellite_confirmed": hotspots > 0,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "events": part_events,
+        "size": part_events.len(),
+    }).to_string()
+}
+
+/// Upstream has no id, so the report time and the location identify an event.
+fn make_event_id(date: &str, time: &str, latitude: f64, longitude: f64) -> String {
+    format!("{}{}_{:.4}_{:.4}", digits(date), digits(time), latitude, longitude)
+}
+
+/// Report time in KST from date digits like `20190405` and time digits like `142500` or `1425`.
+fn parse_reported_time(date: &str, time: &str) -> Option<i64> {
+    let time = format!("{:0<6}", digits(time));
+
+    util::kst()
+        .datetime_from_str(&format!("{}{}", digits(date), time), "%Y%m%d%H%M%S")
+        .ok()
+        .map(|time| time.timestamp())
+}
+
+fn digits(s: &str) -> String {
+    s.chars().filter(|ch| ch.is_ascii_digit()).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRE_EVENTS_JSON: &str = include_str!("../tests/fixtures/fire_events.json");
+
+    fn fixture_records() -> Vec<JsonValue> {
+        let v = serde_json::from_str::<JsonValue>(FIRE_EVENTS_JSON).unwrap();
+        v[0].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn parse_fixture_events() {
+        let events = parse_fire_events(FIRE_EVENTS_JSON).unwrap();
+
+        // The one without decimal coordinates is dropped.
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].id, "20200301123000_36.5567_128.5123");
+        assert!(events[0].status_code == FireStatusCode::Extinguished);
+        assert_eq!(events[0].address, "경상북도 안동시 풍천면 광덕리");
+        assert_eq!(events[0].reported_time, Some(1583033400));
+
+        assert_eq!(events[1].id, "202003011425_37.2301_129.0412");
+        assert!(events[1].status == FireStatus::Fire);
+        assert_eq!(events[1].reported_time, Some(1583040300));
+
+        assert!(events[2].status_code == FireStatusCode::Unknown("09".into()));
+        assert!(events[2].status == FireStatus::Extinguished);
+    }
+
+    #[test]
+    fn detail_from_fixture() {
+        let records = fixture_records();
+
+        let detail = FireEventDetail::new(&records[0]);
+        assert_eq!(detail.extinguished_date.as_ref().map(|s| s.as_str()), Some("20200301"));
+        assert_eq!(detail.extinguished_time.as_ref().map(|s| s.as_str()), Some("154000"));
+        assert_eq!(detail.damage_area, Some(1.5));
+        assert_eq!(detail.cause.as_ref().map(|s| s.as_str()), Some("쓰레기 소각"));
+        assert_eq!(detail.raw["frfrInfoId"], "191345");
+
+        // Blank fields fall back to the other key or stay unknown.
+        let detail = FireEventDetail::new(&records[1]);
+        assert_eq!(detail.extinguished_date, None);
+        assert_eq!(detail.extinguished_time, None);
+        assert_eq!(detail.damage_area, Some(0.3));
+        assert_eq!(detail.cause.as_ref().map(|s| s.as_str()), Some("입산자 실화"));
+
+        let detail = FireEventDetail::new(&records[2]);
+        assert_eq!(detail.damage_area, None);
+        assert_eq!(detail.cause, None);
+    }
+
+    #[test]
+    fn status_code_from_code_and_label() {
+        let codes = [
+            ("01", "접수", FireStatus::Fire),
+            ("02", "진화중", FireStatus::Fire),
+            ("03", "진화완료", FireStatus::Extinguished),
+            ("04", "뒷불감시", FireStatus::Extinguished),
+            ("05", "산불외종료", FireStatus::Clear),
+            ("09", "알수없음", FireStatus::Extinguished),
+        ];
+
+        for &(code, label, status) in codes.iter() {
+            let status_code = FireStatusCode::from_code(code);
+
+            assert_eq!(status_code.code(), code);
+            assert_eq!(status_code.label(), label);
+            assert!(status_code.status() == status, "{}", code);
+        }
+
+        assert!(FireStatusCode::from_code("") == FireStatusCode::Unknown(String::new()));
+    }
+
+    #[test]
+    fn parse_reported_time_in_kst() {
+        assert_eq!(parse_reported_time("20200301", "123000"), Some(1583033400));
+        assert_eq!(parse_reported_time("2020-03-01", "12:30"), Some(1583033400));
+        assert_eq!(parse_reported_time("20200301", "000000"), Some(1583020800 - 9 * 60 * 60));
+        assert_eq!(parse_reported_time("202003", "123000"), None);
+        assert_eq!(parse_reported_time("20200301", "250000"), None);
+        assert_eq!(parse_reported_time("", ""), None);
+    }
+
+    #[test]
+    fn event_id_from_digits_and_position() {
+        assert_eq!(make_event_id("2020-03-01", "12:30:00", 36.55671, 128.51229),
+            "20200301123000_36.5567_128.5123");
+        assert_eq!(make_event_id("20200301", "1425", 37.0, 129.0), "202003011425_37.0000_129.0000");
+    }
+
+    #[test]
+    fn parse_drops_duplicate_events() {
+        let record = |stcd: &str| json!({
+            "frfrPrgrsStcd": stcd,
+            "frfrSttmnLctnYcrd": "36.5",
+            "frfrSttmnLctnXcrd": "128.0",
+            "frfrSttmnAddr": "경상북도",
+            "frfrSttmnDt": "20200301",
+            "frfrSttmnHms": "123000",
+        });
+        let json_str = json!([[record("01"), record("02")]]).to_string();
+
+        let events = parse_fire_events(&json_str).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert!(events[0].status_code == FireStatusCode::Reported);
     }
 }
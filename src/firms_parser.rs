@@ -0,0 +1,344 @@
+use std::fmt;
+
+use chrono::NaiveDateTime;
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Instrument {
+    Modis,
+    Viirs,
+}
+
+impl Instrument {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instrument::Modis => "modis",
+            Instrument::Viirs => "viirs",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "modis" => Some(Instrument::Modis),
+            "viirs" => Some(Instrument::Viirs),
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DayNight {
+    Day,
+    Night,
+}
+
+impl DayNight {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DayNight::Day => "day",
+            DayNight::Night => "night",
+        }
+    }
+}
+
+
+/// Hotspot record of a FIRMS CSV file.
+#[derive(Clone)]
+pub struct FirmsRecord {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub brightness: f32, // kelvin
+    pub radiative_power: f32, // MW
+    pub confidence: f32, // 0~100
+    pub instrument: Instrument,
+    pub satellite: String,
+    pub daynight: Option<DayNight>,
+    pub time: i64,
+}
+
+
+#[derive(Debug)]
+pub enum ParseError {
+    NoHeader,
+    MissingColumn(&'static str),
+    UnknownInstrument,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoHeader => write!(f, "There is no CSV header"),
+            ParseError::MissingColumn(name) => write!(f, "Can't find the {} column", name),
+            ParseError::UnknownInstrument => write!(f, "Can't tell MODIS or VIIRS from the header"),
+        }
+    }
+}
+
+
+struct Columns {
+    latitude: usize,
+    longitude: usize,
+    brightness: usize,
+    radiative_power: usize,
+    confidence: usize,
+    acq_date: usize,
+    acq_time: usize,
+    satellite: Option<usize>,
+    instrument: Option<usize>,
+    daynight: Option<usize>,
+}
+
+impl Columns {
+    fn find(header: &[&str]) -> Result<(Self, Option<Instrument>), ParseError> {
+        let find = |name: &str| header.iter().position(|&col| col == name);
+        let require = |name: &'static str| find(name).ok_or(ParseError::MissingColumn(name));
+
+        // MODIS and VIIRS name the brightness column differently.
+        let (brightness, instrument) = match (find("brightness"), find("bright_ti4")) {
+            (Some(col), _) => (col, Some(Instrument::Modis)),
+            (None, Some(col)) => (col, Some(Instrument::Viirs)),
+            (None, None) => return Err(ParseError::MissingColumn("brightness")),
+        };
+
+        let columns = Columns {
+            latitude: require("latitude")?,
+            longitude: require("longitude")?,
+            brightness,
+            radiative_power: require("frp")?,
+            confidence: require("confidence")?,
+            acq_date: require("acq_date")?,
+            acq_time: require("acq_time")?,
+            satellite: find("satellite"),
+            instrument: find("instrument"),
+            daynight: find("daynight"),
+        };
+
+        Ok((columns, instrument))
+    }
+}
+
+
+/// Parse a FIRMS active fire CSV by its header.
+/// Rows which can't be parsed are skipped.
+pub fn parse_firms_csv(csv: &str) -> Result<Vec<FirmsRecord>, ParseError> {
+    let mut lines = csv.lines().filter(|ln| !ln.trim().is_empty());
+
+    let header = lines.next()
+        .map(split_row)
+        .ok_or(ParseError::NoHeader)?
+        .into_iter()
+        .map(|col| col.to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    let header = header.iter().map(|col| col.as_str()).collect::<Vec<_>>();
+
+    let (columns, header_instrument) = Columns::find(&header)?;
+
+    if header_instrument.is_none() && columns.instrument.is_none() {
+        return Err(ParseError::UnknownInstrument);
+    }
+
+    let records = lines
+        .map(split_row)
+        .filter(|row| row.len() == header.len())
+        .filter_map(|row| {
+            let instrument = columns.instrument
+                .and_then(|col| Instrument::from_name(row[col]))
+                .or(header_instrument)?;
+
+            let date_time = format!("{} {:0>4}", row[columns.acq_date], row[columns.acq_time]);
+            let time = NaiveDateTime::parse_from_str(&date_time, "%Y-%m-%d %H%M").ok()?;
+
+            Some(FirmsRecord {
+                latitude: row[columns.latitude].parse().ok()?,
+                longitude: row[columns.longitude].parse().ok()?,
+                brightness: row[columns.brightness].parse().ok()?,
+                radiative_power: row[columns.radiative_power].parse().ok()?,
+                confidence: parse_confidence(row[columns.confidence])?,
+                instrument,
+                satellite: columns.satellite
+                    .map(|col| satellite_name(row[col]))
+                    .unwrap_or_else(|| instrument.name().to_owned()),
+                daynight: columns.daynight.and_then(|col| match row[col] {
+                    "D" => Some(DayNight::Day),
+                    "N" => Some(DayNight::Night),
+                    _ => None,
+                }),
+                time: time.timestamp(),
+            })
+        })
+        .collect();
+
+    Ok(records)
+}
+
+
+fn split_row(line: &str) -> Vec<&str> {
+    line.split(',')
+        .map(|col| col.trim().trim_matches('"'))
+        .collect()
+}
+
+/// MODIS gives 0~100 and VIIRS gives low, nominal or high, counted as 0, 50 and 100.
+fn parse_confidence(confidence: &str) -> Option<f32> {
+    match confidence {
+        "l" | "low" => Some(0.0),
+        "n" | "nominal" => Some(50.0),
+        "h" | "high" => Some(100.0),
+        _ => confidence.parse().ok(),
+    }
+}
+
+fn satellite_name(code: &str) -> String {
+    match code {
+        "T" | "Terra" => "Terra",
+        "A" | "Aqua" => "Aqua",
+        "N" | "NPP" => "Suomi NPP",
+        "1" | "N20" => "NOAA-20",
+        "2" | "N21" => "NOAA-21",
+        _ => code,
+    }.to_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODIS_CSV: &str = include_str!("../tests/fixtures/modis.csv");
+    const VIIRS_CSV: &str = include_str!("../tests/fixtures/viirs.csv");
+
+    fn header_columns(header: &str) -> Result<(Columns, Option<Instrument>), ParseError> {
+        Columns::find(&split_row(header))
+    }
+
+    #[test]
+    fn map_modis_header() {
+        let (columns, instrument) = header_columns(MODIS_CSV.lines().next().unwrap()).unwrap();
+
+        assert_eq!(instrument, Some(Instrument::Modis));
+        assert_eq!((columns.latitude, columns.longitude, columns.brightness), (0, 1, 2));
+        assert_eq!((columns.acq_date, columns.acq_time), (5, 6));
+        assert_eq!((columns.confidence, columns.radiative_power), (8, 11));
+        assert_eq!((columns.satellite, columns.instrument, columns.daynight), (Some(7), None, Some(12)));
+    }
+
+    #[test]
+    fn map_viirs_header() {
+        let (columns, instrument) = header_columns(VIIRS_CSV.lines().next().unwrap()).unwrap();
+
+        assert_eq!(instrument, Some(Instrument::Viirs));
+        assert_eq!(columns.brightness, 2);
+        assert_eq!((columns.confidence, columns.radiative_power), (8, 11));
+    }
+
+    #[test]
+    fn parse_modis_csv() {
+        let records = parse_firms_csv(MODIS_CSV).unwrap();
+
+        // The row with a bad latitude and the short row are skipped.
+        assert_eq!(records.len(), 3);
+
+        let record = &records[0];
+        assert_eq!((record.latitude, record.longitude), (38.2051, 128.5382));
+        assert_eq!(record.brightness, 336.7);
+        assert_eq!(record.radiative_power, 52.6);
+        assert_eq!(record.confidence, 87.0);
+        assert_eq!(record.instrument, Instrument::Modis);
+        assert_eq!(record.satellite, "Terra");
+        assert_eq!(record.daynight, Some(DayNight::Night));
+        assert_eq!(record.time, 1554397500); // 2019-04-04 17:05 UTC
+
+        // A time without leading zeros is 00:45.
+        let record = &records[2];
+        assert_eq!(record.satellite, "Aqua");
+        assert_eq!(record.daynight, Some(DayNight::Day));
+        assert_eq!(record.time, 1554425100);
+    }
+
+    #[test]
+    fn parse_viirs_csv() {
+        let records = parse_firms_csv(VIIRS_CSV).unwrap();
+        assert_eq!(records.len(), 3);
+
+        assert!(records.iter().all(|record| record.instrument == Instrument::Viirs));
+        assert_eq!(records[0].brightness, 367.0);
+        assert_eq!(records[0].time, 1554399720); // 2019-04-04 17:42 UTC
+        assert_eq!(records[2].daynight, Some(DayNight::Day));
+    }
+
+    #[test]
+    fn viirs_letter_confidence() {
+        let records = parse_firms_csv(VIIRS_CSV).unwrap();
+        let confidences = records.iter().map(|record| record.confidence).collect::<Vec<_>>();
+        assert_eq!(confidences, vec![100.0, 50.0, 0.0]);
+
+        // MODIS gives numbers which are kept as they are.
+        let records = parse_firms_csv(MODIS_CSV).unwrap();
+        let confidences = records.iter().map(|record| record.confidence).collect::<Vec<_>>();
+        assert_eq!(confidences, vec![87.0, 64.0, 42.0]);
+
+        assert_eq!(parse_confidence("nominal"), Some(50.0));
+        assert_eq!(parse_confidence("x"), None);
+    }
+
+    #[test]
+    fn map_satellite_names() {
+        let records = parse_firms_csv(VIIRS_CSV).unwrap();
+        assert_eq!(records[0].satellite, "Suomi NPP");
+        assert_eq!(records[1].satellite, "NOAA-20");
+
+        assert_eq!(satellite_name("N21"), "NOAA-21");
+        assert_eq!(satellite_name("Aqua"), "Aqua");
+        assert_eq!(satellite_name("GOES"), "GOES");
+    }
+
+    #[test]
+    fn parse_reordered_columns() {
+        let csv = "\"acq_time\",\"daynight\",\"frp\",\"confidence\",\"bright_ti4\",\"acq_date\",\"longitude\",\"latitude\"\n\
+            1742,D,25.4,h,367.0,2019-04-04,128.53987,38.20412\n";
+
+        let records = parse_firms_csv(csv).unwrap();
+        assert_eq!(records.len(), 1);
+
+        let record = &records[0];
+        assert_eq!((record.latitude, record.longitude), (38.20412, 128.53987));
+        assert_eq!(record.brightness, 367.0);
+        assert_eq!(record.radiative_power, 25.4);
+        assert_eq!(record.confidence, 100.0);
+        assert_eq!(record.daynight, Some(DayNight::Day));
+        assert_eq!(record.time, 1554399720);
+
+        // Without the satellite column the instrument is the satellite.
+        assert_eq!(record.satellite, "viirs");
+    }
+
+    #[test]
+    fn instrument_column_wins() {
+        let csv = "latitude,longitude,brightness,frp,confidence,acq_date,acq_time,instrument\n\
+            38.2,128.5,336.7,52.6,87,2019-04-04,1705,VIIRS\n";
+
+        let records = parse_firms_csv(csv).unwrap();
+        assert_eq!(records[0].instrument, Instrument::Viirs);
+    }
+
+    #[test]
+    fn reject_missing_header() {
+        let csv = MODIS_CSV.replacen(",frp,", ",power,", 1);
+        match parse_firms_csv(&csv) {
+            Err(ParseError::MissingColumn("frp")) => (),
+            result => panic!("Unexpected result: {:?}", result.map(|records| records.len())),
+        }
+
+        let csv = MODIS_CSV.replacen("brightness", "temperature", 1);
+        match parse_firms_csv(&csv) {
+            Err(ParseError::MissingColumn("brightness")) => (),
+            result => panic!("Unexpected result: {:?}", result.map(|records| records.len())),
+        }
+
+        match parse_firms_csv("\n\n") {
+            Err(ParseError::NoHeader) => (),
+            result => panic!("Unexpected result: {:?}", result.map(|records| records.len())),
+        }
+    }
+}
@@ -10,6 +10,7 @@ extern crate rand;
 
 mod db;
 mod util;
+mod district;
 mod logger;
 mod task_scheduler;
 mod captcha_sys;
@@ -18,9 +19,16 @@ mod shelter_sys;
 mod cctv_sys;
 mod fire_sys;
 mod wind_sys;
+mod aws_parser;
+mod wind_source;
+mod wind_field;
 mod active_fire_sys;
+mod firms_parser;
+mod fire_correlation;
 mod fire_forecast_sys;
+mod fire_spread_sys;
 mod danger_place_sys;
+mod event_sys;
 
 
 use std::{env, env::VarError};
@@ -96,6 +104,9 @@ fn main() {
     };
 
 
+    district::init_district();
+
+
     let mut scheduler = TaskSchedulerBuilder::new()
         .n_workers(6)
         .period_resolution(Duration::new(0, 100/*ms*/ * 1_000_000));
@@ -155,20 +166,47 @@ fn main() {
     ])
     .mount("/", routes![
         fire_sys::get_fire_event_map,
+        fire_sys::get_fire_event_detail,
+        fire_sys::get_fire_event_history,
+        fire_sys::get_fire_event_changes,
     ])
     .mount("/", routes![
         wind_sys::get_wind_map_metadata,
         wind_sys::get_wind_map,
+        wind_sys::get_wind,
+        wind_sys::get_wind_grid,
+        wind_sys::get_wind_grid_json,
+        wind_sys::get_wind_tile,
+        wind_sys::get_wind_map_history,
+        wind_sys::get_weather_stations,
+        wind_sys::get_humidity_map,
+        wind_sys::get_wind_map_history_metadata,
+        wind_sys::get_wind_map_history_img,
     ])
     .mount("/", routes![
         active_fire_sys::get_active_fire_map,
+        active_fire_sys::get_active_fire_history,
+        active_fire_sys::get_active_fire_daily,
     ])
     .mount("/", routes![
         fire_forecast_sys::get_fire_forecast_map,
+        fire_forecast_sys::get_fire_forecast_geojson,
+        fire_forecast_sys::get_fire_forecast,
+        fire_forecast_sys::get_fire_forecast_history,
+    ])
+    .mount("/", routes![
+        fire_spread_sys::get_fire_spread,
     ])
     .mount("/", routes![
         danger_place_sys::get_danger_place_map,
     ])
+    .mount("/", routes![
+        event_sys::get_events,
+    ])
+    .attach(AdHoc::on_attach("Event clients", |rocket| {
+        event_sys::init_event_sys(rocket.config().workers as usize);
+        Ok(rocket)
+    }))
     .launch();
 
 
@@ -21,6 +21,8 @@ use chrono::Utc;
 
 use crate::db;
 use crate::util;
+use crate::district;
+use crate::event_sys::{self, Layer};
 use crate::captcha_sys::verify_and_remove_captcha;
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
@@ -167,6 +169,8 @@ fn make_report_map() -> Result<String, String> {
         .map(|reports| {
             let part_jsons = reports.iter()
                 .map(|r| {
+                    let district = district::find_district(r.latitude, r.longitude);
+
                     json!({
                         "id": r.id,
                         "user_id": r.user_id,
@@ -174,6 +178,8 @@ fn make_report_map() -> Result<String, String> {
                         "longitude": r.longitude,
                         "created_time": r.created_time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
                         "lvl": r.lvl,
+                        "district": district.map(|d| &d.code),
+                        "district_name": district.map(|d| &d.name),
                     })
                 })
                 .collect::<Vec<_>>();
@@ -187,7 +193,7 @@ fn make_report_map() -> Result<String, String> {
 }
 
 fn update_report_map(data: String) {
-    *REPORT_MAP_CACHE.write().unwrap() = data;
+    event_sys::update_cache(&REPORT_MAP_CACHE, data, Layer::Reports);
 }
 
 #[get("/report?<id>")]
@@ -202,6 +208,8 @@ pub fn get_report(id: i32) -> JsonResult {
 
     db::get_report(id)
         .map(|r| {
+            let district = district::find_district(r.latitude, r.longitude);
+
             let data = json!({
                 "id": r.id,
                 "user_id": r.user_id,
@@ -211,6 +219,8 @@ pub fn get_report(id: i32) -> JsonResult {
                 "lvl": r.lvl,
                 "description": r.description,
                 "img_path": r.img_path,
+                "district": district.map(|d| &d.code),
+                "district_name": district.map(|d| &d.name),
             }).to_string();
 
             {
@@ -17,6 +17,8 @@ use serde_json::{json, Value as JsonValue};
 
 use crate::db;
 use crate::util;
+use crate::district::{self, District};
+use crate::event_sys::{self, Layer};
 use crate::captcha_sys::verify_and_remove_captcha;
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
@@ -62,6 +64,7 @@ struct Shelter {
     info: String,
     recent_good: i32,
     recent_bad: i32,
+    district: Option<&'static District>,
 
     cached: bool,
     json_cache: String,
@@ -78,6 +81,7 @@ impl Shelter {
             info,
             recent_good: 0,
             recent_bad: 0,
+            district: district::find_district(latitude, longitude),
 
             cached: false,
             json_cache: String::new(),
@@ -98,6 +102,8 @@ impl Shelter {
             "info": self.info,
             "good": self.recent_good,
             "bad": self.recent_bad,
+            "district": self.district.map(|d| &d.code),
+            "district_name": self.district.map(|d| &d.name),
         }).to_string();
 
         self.cached = true;
@@ -455,7 +461,7 @@ fn init_db_and_shelters() {
 }
 
 fn update_shelter_data(data: String) {
-    *SHELTER_DATA.write().unwrap() = data;
+    event_sys::update_cache(&SHELTER_DATA, data, Layer::Shelters);
 }
 
 fn build_shelter_data() -> String {
@@ -469,6 +475,8 @@ fn build_shelter_data() -> String {
                 "longitude": s.longitude,
                 "good": s.recent_good,
                 "bad": s.recent_bad,
+                "district": s.district.map(|d| &d.code),
+                "district_name": s.district.map(|d| &d.name),
             })
         })
         .collect::<Vec<_>>()
@@ -1,11 +1,16 @@
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::time::{UNIX_EPOCH, Duration, SystemTime};
 use std::f64;
 
 use rand::{
     thread_rng, Rng,
     distributions,
 };
+use chrono::{FixedOffset, Utc};
+
+
+pub const KST_OFFSET: i32 = 9 * 60 * 60; // seconds
 
 
 pub fn generate_rand_id(length: usize) -> String {
@@ -44,7 +49,7 @@ pub fn extract_text_from_html(html: &str) -> String {
 }
 
 const LL_RADIUS: f64 = 6378136.98;
-const LL_RANGE: f64 = LL_RADIUS * f64::consts::PI * 2.0;
+pub const LL_RANGE: f64 = LL_RADIUS * f64::consts::PI * 2.0;
 const LL_LON2X: f64 = LL_RANGE / 360.0;
 const LL_RAD_OVER_DEG: f64 = f64::consts::PI / 180.0;
 pub fn transform_lonlat(longitude: f64, latitude: f64) -> (f64, f64) {
@@ -61,4 +66,25 @@ pub fn transform_lonlat(longitude: f64, latitude: f64) -> (f64, f64) {
         let y = (1.0 / y.cos() + y.tan()).log(f64::consts::E);
         (x, y * LL_RADIUS)
     }
-}
\ No newline at end of file
+}
+
+/// Korea Standard Time of the upstream data.
+pub fn kst() -> FixedOffset {
+    FixedOffset::east(KST_OFFSET)
+}
+
+/// Current unix time truncated to the minute.
+pub fn current_minute() -> i64 {
+    let now = Utc::now().timestamp();
+    now - now % 60
+}
+
+/// Current time truncated to the second like the times saved in DB.
+pub fn current_time() -> SystemTime {
+    UNIX_EPOCH + Duration::new(Utc::now().timestamp() as u64, 0)
+}
+
+/// Unix time of a time saved in DB.
+pub fn to_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
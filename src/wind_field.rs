@@ -0,0 +1,757 @@
+use std::{
+    f64,
+    sync::{mpsc, Arc},
+    collections::HashMap,
+};
+use cgmath::{MetricSpace, Point2, Vector2};
+use serde_json::{Value as JsonValue, json};
+use threadpool::ThreadPool;
+
+
+pub const DEFAULT_IDW_POWER: f64 = 3.0;
+const INDEX_CELL_SIZE: f64 = 64.0;
+const ROWS_PER_JOB: usize = 16;
+
+
+type StationWind = (f64, f64, Vector2<f64>);
+
+
+/// How the wind between stations is estimated.
+#[derive(Clone)]
+pub enum InterpolationMethod {
+    /// Inverse distance weighting with the stations in `radius` grid cells or every station.
+    Idw { power: f64, radius: Option<f64> },
+    /// Barycentric interpolation in the Delaunay triangle containing the point.
+    Delaunay,
+    /// Wind of the nearest station.
+    Nearest,
+}
+
+impl InterpolationMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InterpolationMethod::Idw { .. } => "idw",
+            InterpolationMethod::Delaunay => "delaunay",
+            InterpolationMethod::Nearest => "nearest",
+        }
+    }
+
+    pub fn to_json(&self, resolution: f64) -> JsonValue {
+        match self {
+            InterpolationMethod::Idw { power, radius } => json!({
+                "method": self.name(),
+                "power": power,
+                "radius": radius.map(|r| r * resolution),
+            }),
+            _ => json!({
+                "method": self.name(),
+            }),
+        }
+    }
+
+    fn build(&self, stations: &[StationWind]) -> Box<dyn Interpolator> {
+        match self {
+            InterpolationMethod::Idw { power, radius } => Box::new(Idw::new(stations, *power, *radius)),
+            InterpolationMethod::Delaunay => Box::new(Delaunay::new(stations)),
+            InterpolationMethod::Nearest => Box::new(Nearest::new(stations)),
+        }
+    }
+}
+
+
+trait Interpolator: Send + Sync {
+    /// Wind at `point` and the number of stations it's made from.
+    fn interpolate(&self, stations: &[StationWind], point: Point2<f64>) -> (Vector2<f64>, usize);
+}
+
+
+/// Station winds in grid coordinates used to interpolate the wind field.
+pub struct WindField {
+    pub stations: Vec<StationWind>,
+    pub observed_time: i64,
+    pub method: InterpolationMethod,
+    interpolator: Box<dyn Interpolator>,
+}
+
+impl WindField {
+    pub fn new(stations: Vec<StationWind>, observed_time: i64, method: InterpolationMethod) -> Self {
+        let interpolator = method.build(&stations);
+
+        WindField {
+            stations,
+            observed_time,
+            method,
+            interpolator,
+        }
+    }
+
+    pub fn interpolate(&self, point: Point2<f64>) -> Vector2<f64> {
+        self.interpolator.interpolate(&self.stations, point).0
+    }
+
+    /// Wind at `point` and the number of stations used for it.
+    pub fn interpolate_with_count(&self, point: Point2<f64>) -> (Vector2<f64>, usize) {
+        self.interpolator.interpolate(&self.stations, point)
+    }
+}
+
+
+/// Grid of station indices for neighbour queries.
+struct StationIndex {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+}
+
+impl StationIndex {
+    fn new(stations: &[StationWind]) -> Self {
+        let mut cells = HashMap::new();
+        let mut min_cell = (i32::MAX, i32::MAX);
+        let mut max_cell = (i32::MIN, i32::MIN);
+
+        for (i, (x, y, _)) in stations.iter().enumerate() {
+            let (cx, cy) = index_cell(*x, *y);
+
+            min_cell = (min_cell.0.min(cx), min_cell.1.min(cy));
+            max_cell = (max_cell.0.max(cx), max_cell.1.max(cy));
+
+            cells.entry((cx, cy))
+                .or_insert_with(Vec::new)
+                .push(i);
+        }
+
+        StationIndex {
+            cells,
+            min_cell,
+            max_cell,
+        }
+    }
+
+    /// Stations within `radius` of `point` with their squared distances.
+    fn within(&self, stations: &[StationWind], point: Point2<f64>, radius: f64) -> Vec<(usize, f64)> {
+        let (min_cx, min_cy) = index_cell(point.x - radius, point.y - radius);
+        let (max_cx, max_cy) = index_cell(point.x + radius, point.y + radius);
+        let radius2 = radius * radius;
+
+        let mut found = Vec::new();
+
+        for cy in min_cy.max(self.min_cell.1)..=max_cy.min(self.max_cell.1) {
+            for cx in min_cx.max(self.min_cell.0)..=max_cx.min(self.max_cell.0) {
+                for &i in self.cells.get(&(cx, cy)).into_iter().flatten() {
+                    let distance = station_distance2(&stations[i], point);
+
+                    if distance <= radius2 {
+                        found.push((i, distance));
+                    }
+                }
+            }
+        }
+
+        found
+    }
+
+    /// `k` nearest stations of `point` with their squared distances, nearest first.
+    fn nearest(&self, stations: &[StationWind], point: Point2<f64>, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 || self.cells.is_empty() || !point.x.is_finite() || !point.y.is_finite() {
+            return Vec::new();
+        }
+
+        // Cells are compared in i64 since a far point saturates its cell.
+        let (px, py) = index_cell(point.x, point.y);
+        let (px, py) = (px as i64, py as i64);
+        let (min_x, min_y) = (self.min_cell.0 as i64, self.min_cell.1 as i64);
+        let (max_x, max_y) = (self.max_cell.0 as i64, self.max_cell.1 as i64);
+
+        let mut found = Vec::new();
+
+        // Rings closer than this have no station.
+        let mut ring = (min_x - px).max(px - max_x).max(min_y - py).max(py - max_y).max(0);
+
+        loop {
+            // Visit the cells on the border of the square of `ring` cells around the point.
+            for cy in (py - ring).max(min_y)..=(py + ring).min(max_y) {
+                let border_row = cy == py - ring || cy == py + ring;
+                let cxs = if border_row {
+                    ((px - ring).max(min_x)..=(px + ring).min(max_x)).collect::<Vec<_>>()
+                }
+                else if ring == 0 {
+                    vec![px]
+                }
+                else {
+                    vec![px - ring, px + ring]
+                };
+
+                for cx in cxs.into_iter().filter(|&cx| cx >= min_x && cx <= max_x) {
+                    for &i in self.cells.get(&(cx as i32, cy as i32)).into_iter().flatten() {
+                        found.push((i, station_distance2(&stations[i], point)));
+                    }
+                }
+            }
+
+            let covered = px - ring <= min_x && px + ring >= max_x
+                && py - ring <= min_y && py + ring >= max_y;
+
+            if covered || found.len() >= k {
+                found.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+                // Stations out of the visited square are at least this far.
+                let bound = (point.x - ((px - ring) as f64 * INDEX_CELL_SIZE))
+                    .min((px + ring + 1) as f64 * INDEX_CELL_SIZE - point.x)
+                    .min(point.y - ((py - ring) as f64 * INDEX_CELL_SIZE))
+                    .min((py + ring + 1) as f64 * INDEX_CELL_SIZE - point.y);
+
+                if covered || found[k - 1].1 <= bound * bound {
+                    found.truncate(k);
+                    return found;
+                }
+            }
+
+            ring += 1;
+        }
+    }
+}
+
+
+/// Inverse distance weighting with the stations in `radius` or every station.
+/// With the default power the far stations still add up to a large share of the weight,
+/// so only the radius limited one uses the index.
+struct Idw {
+    power: f64,
+    radius: Option<f64>,
+    index: StationIndex,
+}
+
+impl Idw {
+    fn new(stations: &[StationWind], power: f64, radius: Option<f64>) -> Self {
+        Idw {
+            power,
+            radius,
+            index: StationIndex::new(stations),
+        }
+    }
+}
+
+impl Interpolator for Idw {
+    fn interpolate(&self, stations: &[StationWind], point: Point2<f64>) -> (Vector2<f64>, usize) {
+        let radius = match self.radius {
+            Some(radius) => radius,
+            None => return (idw_all(stations, point, self.power), stations.len()),
+        };
+
+        let neighbours = self.index.within(stations, point, radius);
+
+        let mut total_weight = 0.0;
+        let mut total_wind = Vector2::new(0.0, 0.0);
+
+        for &(i, distance) in &neighbours {
+            let weight = idw_weight(distance, self.power);
+            total_weight += weight;
+            total_wind += weight * stations[i].2;
+        }
+
+        if total_weight > 0.0 {
+            (total_wind / total_weight, neighbours.len())
+        }
+        else {
+            // Nothing in the search radius.
+            nearest_wind(&self.index, stations, point)
+        }
+    }
+}
+
+
+struct Nearest {
+    index: StationIndex,
+}
+
+impl Nearest {
+    fn new(stations: &[StationWind]) -> Self {
+        Nearest {
+            index: StationIndex::new(stations),
+        }
+    }
+}
+
+impl Interpolator for Nearest {
+    fn interpolate(&self, stations: &[StationWind], point: Point2<f64>) -> (Vector2<f64>, usize) {
+        nearest_wind(&self.index, stations, point)
+    }
+}
+
+
+struct Delaunay {
+    triangles: Vec<[usize; 3]>,
+    index: HashMap<(i32, i32), Vec<usize>>,
+    stations: StationIndex,
+}
+
+impl Delaunay {
+    fn new(stations: &[StationWind]) -> Self {
+        let triangles = triangulate(stations);
+
+        // Index triangles by the cells their bounding box overlaps.
+        let mut index = HashMap::new();
+
+        for (t, tri) in triangles.iter().enumerate() {
+            let xs = tri.iter().map(|&i| stations[i].0);
+            let ys = tri.iter().map(|&i| stations[i].1);
+            let (min_cx, min_cy) = index_cell(xs.clone().fold(f64::MAX, f64::min), ys.clone().fold(f64::MAX, f64::min));
+            let (max_cx, max_cy) = index_cell(xs.fold(f64::MIN, f64::max), ys.fold(f64::MIN, f64::max));
+
+            for cy in min_cy..=max_cy {
+                for cx in min_cx..=max_cx {
+                    index.entry((cx, cy)).or_insert_with(Vec::new).push(t);
+                }
+            }
+        }
+
+        Delaunay {
+            triangles,
+            index,
+            stations: StationIndex::new(stations),
+        }
+    }
+}
+
+impl Interpolator for Delaunay {
+    fn interpolate(&self, stations: &[StationWind], point: Point2<f64>) -> (Vector2<f64>, usize) {
+        let candidates = self.index.get(&index_cell(point.x, point.y));
+
+        for &t in candidates.into_iter().flatten() {
+            let [a, b, c] = self.triangles[t];
+            let (ax, ay, wa) = &stations[a];
+            let (bx, by, wb) = &stations[b];
+            let (cx, cy, wc) = &stations[c];
+
+            let det = (by - cy) * (ax - cx) + (cx - bx) * (ay - cy);
+            let l1 = ((by - cy) * (point.x - cx) + (cx - bx) * (point.y - cy)) / det;
+            let l2 = ((cy - ay) * (point.x - cx) + (ax - cx) * (point.y - cy)) / det;
+            let l3 = 1.0 - l1 - l2;
+
+            if l1 >= 0.0 && l2 >= 0.0 && l3 >= 0.0 {
+                return (wa * l1 + wb * l2 + wc * l3, 3);
+            }
+        }
+
+        // Outside of the convex hull.
+        nearest_wind(&self.stations, stations, point)
+    }
+}
+
+
+/// Interpolate every cell of a `width` x `height` grid, splitting rows over `n_workers` threads.
+/// The result is row-major from the bottom row(y = 0).
+pub fn interpolate_grid(field: &Arc<WindField>, width: usize, height: usize, n_workers: usize)
+    -> Vec<Vector2<f64>> {
+
+    let pool = ThreadPool::new(n_workers);
+    let (tx, rx) = mpsc::channel();
+
+    for begin in (0..height).step_by(ROWS_PER_JOB) {
+        let end = height.min(begin + ROWS_PER_JOB);
+        let field = field.clone();
+        let tx = tx.clone();
+
+        pool.execute(move || {
+            let mut rows = Vec::with_capacity((end - begin) * width);
+
+            for y in begin..end {
+                for x in 0..width {
+                    rows.push(field.interpolate(Point2::new(x as f64, y as f64)));
+                }
+            }
+
+            let _ = tx.send((begin, rows));
+        });
+    }
+
+    drop(tx);
+
+    let mut grid = vec![Vector2::new(0.0, 0.0); width * height];
+
+    for (begin, rows) in rx.iter() {
+        let offset = begin * width;
+        grid[offset..(offset + rows.len())].copy_from_slice(&rows);
+    }
+
+    grid
+}
+
+
+fn idw_weight(distance2: f64, power: f64) -> f64 {
+    if distance2 < 1.0 {
+        1.0
+    }
+    else if power == 3.0 {
+        // Much faster than powf for the default power.
+        1.0 / (distance2 * distance2.sqrt())
+    }
+    else {
+        1.0 / distance2.powf(power / 2.0)
+    }
+}
+
+fn idw_all(stations: &[StationWind], point: Point2<f64>, power: f64) -> Vector2<f64> {
+    let mut total_weight = 0.0;
+    let mut total_wind = Vector2::new(0.0, 0.0);
+
+    for stn in stations {
+        let weight = idw_weight(station_distance2(stn, point), power);
+        total_weight += weight;
+        total_wind += weight * stn.2;
+    }
+
+    total_wind / total_weight
+}
+
+fn index_cell(x: f64, y: f64) -> (i32, i32) {
+    ((x / INDEX_CELL_SIZE).floor() as i32, (y / INDEX_CELL_SIZE).floor() as i32)
+}
+
+fn nearest_wind(index: &StationIndex, stations: &[StationWind], point: Point2<f64>) -> (Vector2<f64>, usize) {
+    index.nearest(stations, point, 1)
+        .first()
+        .map(|&(i, _)| (stations[i].2, 1))
+        .unwrap_or((Vector2::new(0.0, 0.0), 0))
+}
+
+fn station_distance2(station: &StationWind, point: Point2<f64>) -> f64 {
+    point.distance2(Point2::new(station.0, station.1))
+}
+
+/// Bowyer-Watson triangulation of the station points.
+fn triangulate(stations: &[StationWind]) -> Vec<[usize; 3]> {
+    let n = stations.len();
+    if n < 3 {
+        return Vec::new();
+    }
+
+    let mut points = stations.iter()
+        .map(|(x, y, _)| (*x, *y))
+        .collect::<Vec<_>>();
+
+    // Add a super triangle containing every point.
+    let (min_x, min_y, max_x, max_y) = points.iter().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |acc, &(x, y)| (acc.0.min(x), acc.1.min(y), acc.2.max(x), acc.3.max(y)));
+    let size = (max_x - min_x).max(max_y - min_y).max(1.0) * 20.0;
+    let (mid_x, mid_y) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+    points.push((mid_x - size, mid_y - size));
+    points.push((mid_x + size, mid_y - size));
+    points.push((mid_x, mid_y + size));
+
+    let mut triangles = vec![[n, n + 1, n + 2]];
+
+    for p in 0..n {
+        let (px, py) = points[p];
+
+        let (bad, good): (Vec<_>, Vec<_>) = triangles.into_iter()
+            .partition(|tri| in_circumcircle(&points, tri, px, py));
+
+        // Boundary of the hole is the edges not shared by two bad triangles.
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for tri in &bad {
+            for &(a, b) in &[(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                if let Some(pos) = edges.iter().position(|&(c, d)| (c == b && d == a) || (c == a && d == b)) {
+                    edges.swap_remove(pos);
+                }
+                else {
+                    edges.push((a, b));
+                }
+            }
+        }
+
+        triangles = good;
+        triangles.extend(edges.into_iter().map(|(a, b)| [a, b, p]));
+    }
+
+    triangles.into_iter()
+        .filter(|tri| tri.iter().all(|&i| i < n))
+        .collect()
+}
+
+fn in_circumcircle(points: &[(f64, f64)], tri: &[usize; 3], px: f64, py: f64) -> bool {
+    let (ax, ay) = points[tri[0]];
+    let (bx, by) = points[tri[1]];
+    let (cx, cy) = points[tri[2]];
+
+    let d = 2.0 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by));
+    if d == 0.0 {
+        return false;
+    }
+
+    let a2 = ax * ax + ay * ay;
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+    let ux = (a2 * (by - cy) + b2 * (cy - ay) + c2 * (ay - by)) / d;
+    let uy = (a2 * (cx - bx) + b2 * (ax - cx) + c2 * (bx - ax)) / d;
+
+    let r2 = (ax - ux) * (ax - ux) + (ay - uy) * (ay - uy);
+    (px - ux) * (px - ux) + (py - uy) * (py - uy) < r2
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    const WIDTH: usize = 472;
+    const HEIGHT: usize = 828;
+    const TOLERANCE: f64 = 1e-9; // m/s
+    const NEAREST_CNT: usize = 16;
+
+    /// Stations spread like the AWS network over the default grid.
+    fn make_stations(cnt: usize) -> Vec<StationWind> {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        (0..cnt)
+            .map(|_| {
+                let x = rng.gen_range(0.0..WIDTH as f64);
+                let y = rng.gen_range(0.0..HEIGHT as f64);
+                (x, y, Vector2::new(rng.gen_range(-10.0..10.0), rng.gen_range(-10.0..10.0)))
+            })
+            .collect()
+    }
+
+    fn max_diff(a: Vector2<f64>, b: Vector2<f64>) -> f64 {
+        (a.x - b.x).abs().max((a.y - b.y).abs())
+    }
+
+    /// The interpolation of `get_wind_img` before the wind field, summing `1/d^3` of every station.
+    fn baseline_idw(stations: &[StationWind], x: f64, y: f64) -> Vector2<f64> {
+        let (total_weight, total_wind_x, total_wind_y) = stations.iter()
+            .map(|(stn_x, stn_y, wind)| {
+                let distance = (x - stn_x).powi(2) + (y - stn_y).powi(2);
+                let weight = if distance < 1.0 {
+                    1.0
+                } else {
+                    1.0 / distance.powf(1.5)
+                };
+                (weight, weight * wind)
+            })
+            .fold((0.0, 0.0, 0.0), |acc, (weight, wind)| (acc.0 + weight, acc.1 + wind.x, acc.2 + wind.y));
+
+        Vector2::new(total_wind_x / total_weight, total_wind_y / total_weight)
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let stations = make_stations(500);
+        let index = StationIndex::new(&stations);
+
+        for y in (0..HEIGHT).step_by(7) {
+            for x in (0..WIDTH).step_by(7) {
+                let point = Point2::new(x as f64, y as f64);
+
+                let mut expected = stations.iter()
+                    .map(|stn| station_distance2(stn, point))
+                    .collect::<Vec<_>>();
+                expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                expected.truncate(NEAREST_CNT);
+
+                let found = index.nearest(&stations, point, NEAREST_CNT)
+                    .into_iter()
+                    .map(|(_, distance)| distance)
+                    .collect::<Vec<_>>();
+
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn within_matches_brute_force() {
+        let stations = make_stations(500);
+        let index = StationIndex::new(&stations);
+        let radius = 100.0;
+
+        for y in (0..HEIGHT).step_by(13) {
+            for x in (0..WIDTH).step_by(13) {
+                let point = Point2::new(x as f64, y as f64);
+
+                let expected = stations.iter()
+                    .filter(|stn| station_distance2(stn, point) <= radius * radius)
+                    .count();
+
+                assert_eq!(index.within(&stations, point, radius).len(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn default_idw_matches_baseline() {
+        let stations = make_stations(500);
+        let field = WindField::new(stations.clone(), 0,
+            InterpolationMethod::Idw { power: DEFAULT_IDW_POWER, radius: None });
+
+        for y in (0..HEIGHT).step_by(3) {
+            for x in (0..WIDTH).step_by(3) {
+                let (wind, cnt) = field.interpolate_with_count(Point2::new(x as f64, y as f64));
+                let diff = max_diff(wind, baseline_idw(&stations, x as f64, y as f64));
+
+                assert!(diff < TOLERANCE, "{} m/s at ({}, {})", diff, x, y);
+                assert_eq!(cnt, stations.len());
+            }
+        }
+    }
+
+    #[test]
+    fn grid_matches_baseline() {
+        let stations = make_stations(100);
+        let field = Arc::new(WindField::new(stations.clone(), 0,
+            InterpolationMethod::Idw { power: DEFAULT_IDW_POWER, radius: None }));
+
+        let (width, height) = (WIDTH / 4, HEIGHT / 4);
+        let grid = interpolate_grid(&field, width, height, 4);
+
+        for y in 0..height {
+            for x in 0..width {
+                let diff = max_diff(grid[y * width + x], baseline_idw(&stations, x as f64, y as f64));
+                assert!(diff < TOLERANCE, "{} m/s at ({}, {})", diff, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn plain_idw_is_weighted_mean_of_every_station() {
+        let stations = vec![
+            (0.0, 0.0, Vector2::new(1.0, 0.0)),
+            (10.0, 0.0, Vector2::new(0.0, 1.0)),
+            (100.0, 0.0, Vector2::new(-5.0, 0.0)),
+        ];
+        let field = WindField::new(stations, 0, InterpolationMethod::Idw { power: 3.0, radius: None });
+
+        let weights = [1.0 / 125.0, 1.0 / 125.0, 1.0 / 857375.0];
+        let total = weights.iter().sum::<f64>();
+        let expected = Vector2::new(weights[0] - 5.0 * weights[2], weights[1]) / total;
+
+        assert!(max_diff(field.interpolate(Point2::new(5.0, 0.0)), expected) < TOLERANCE);
+    }
+
+    #[test]
+    fn radius_idw_uses_stations_in_radius() {
+        let stations = vec![
+            (10.0, 10.0, Vector2::new(1.0, 0.0)),
+            (20.0, 10.0, Vector2::new(3.0, 0.0)),
+            (300.0, 300.0, Vector2::new(100.0, 0.0)),
+        ];
+        let field = WindField::new(stations, 0, InterpolationMethod::Idw { power: 2.0, radius: Some(50.0) });
+
+        let (wind, cnt) = field.interpolate_with_count(Point2::new(15.0, 10.0));
+        assert!((wind.x - 2.0).abs() < 1e-9);
+        assert_eq!(cnt, 2);
+
+        // Out of every radius, the nearest station is used.
+        let (wind, cnt) = field.interpolate_with_count(Point2::new(150.0, 500.0));
+        assert!((wind.x - 100.0).abs() < 1e-9);
+        assert_eq!(cnt, 1);
+    }
+
+    #[test]
+    fn radius_idw_matches_brute_force() {
+        let stations = make_stations(500);
+        let radius = 40.0;
+        let field = WindField::new(stations.clone(), 0, InterpolationMethod::Idw { power: 2.0, radius: Some(radius) });
+
+        for y in (0..HEIGHT).step_by(5) {
+            for x in (0..WIDTH).step_by(5) {
+                let point = Point2::new(x as f64, y as f64);
+
+                let (total_weight, total_wind) = stations.iter()
+                    .filter(|stn| station_distance2(stn, point) <= radius * radius)
+                    .map(|stn| (idw_weight(station_distance2(stn, point), 2.0), stn.2))
+                    .fold((0.0, Vector2::new(0.0, 0.0)), |acc, (weight, wind)| (acc.0 + weight, acc.1 + weight * wind));
+                if total_weight == 0.0 {
+                    continue;
+                }
+
+                let diff = max_diff(field.interpolate(point), total_wind / total_weight);
+                assert!(diff < TOLERANCE, "{} m/s at ({}, {})", diff, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_uses_nearest_station() {
+        let stations = make_stations(500);
+        let field = WindField::new(stations.clone(), 0, InterpolationMethod::Nearest);
+
+        for y in (0..HEIGHT).step_by(11) {
+            for x in (0..WIDTH).step_by(11) {
+                let point = Point2::new(x as f64, y as f64);
+
+                let expected = stations.iter()
+                    .min_by(|a, b| station_distance2(a, point).partial_cmp(&station_distance2(b, point)).unwrap())
+                    .unwrap();
+
+                assert_eq!(field.interpolate(point), expected.2);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_handles_far_and_invalid_points() {
+        let stations = make_stations(500);
+        let index = StationIndex::new(&stations);
+
+        assert!(index.nearest(&stations, Point2::new(f64::NAN, 10.0), 1).is_empty());
+        assert!(index.nearest(&stations, Point2::new(10.0, f64::INFINITY), 1).is_empty());
+
+        for &point in &[Point2::new(1e12, 10.0), Point2::new(-1e12, -1e12), Point2::new(100.0, 1e9)] {
+            let expected = stations.iter()
+                .map(|stn| station_distance2(stn, point))
+                .fold(f64::MAX, f64::min);
+
+            let found = index.nearest(&stations, point, 1);
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].1, expected);
+        }
+    }
+
+    #[test]
+    fn count_used_stations() {
+        let point = Point2::new(100.0, 100.0);
+
+        let field = WindField::new(make_stations(500), 0, InterpolationMethod::Idw { power: 3.0, radius: None });
+        assert_eq!(field.interpolate_with_count(point).1, 500);
+
+        let field = WindField::new(make_stations(500), 0, InterpolationMethod::Nearest);
+        assert_eq!(field.interpolate_with_count(point).1, 1);
+
+        let field = WindField::new(make_stations(500), 0, InterpolationMethod::Delaunay);
+        assert_eq!(field.interpolate_with_count(point).1, 3);
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench_interpolate_grid`
+    #[test]
+    #[ignore]
+    fn bench_interpolate_grid() {
+        let stations = make_stations(500);
+        let field = Arc::new(WindField::new(stations.clone(), 0,
+            InterpolationMethod::Idw { power: DEFAULT_IDW_POWER, radius: None }));
+
+        let begin = Instant::now();
+        let mut baseline_grid = Vec::with_capacity(WIDTH * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                baseline_grid.push(baseline_idw(&stations, x as f64, y as f64));
+            }
+        }
+        let baseline_time = begin.elapsed();
+
+        let begin = Instant::now();
+        let grid = interpolate_grid(&field, WIDTH, HEIGHT, 4);
+        let grid_time = begin.elapsed();
+
+        let max = baseline_grid.iter().zip(grid.iter())
+            .map(|(&a, &b)| max_diff(a, b))
+            .fold(0.0, f64::max);
+
+        println!("baseline {:?}, 4 workers {:?}, max diff {:e} m/s", baseline_time, grid_time, max);
+        assert!(max < TOLERANCE);
+    }
+}
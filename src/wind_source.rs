@@ -0,0 +1,499 @@
+use std::{
+    env,
+    fs,
+    path::Path,
+    collections::HashMap,
+};
+
+use quick_xml::{
+    self as xml,
+    events::Event,
+};
+use serde_json::Value as JsonValue;
+use chrono::TimeZone;
+
+use crate::util;
+use crate::aws_parser::{self, Observation};
+
+
+const AWS_PAGE_URL: &'static str = "http://www.weather.go.kr/cgi-bin/aws/nph-aws_txt_min";
+
+
+/// Where the station observations come from.
+pub trait WindSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Observations and their observation time.
+    fn fetch(&self) -> Result<(Vec<Observation>, i64), String>;
+}
+
+
+/// Scrape the AWS minutely observation page.
+pub struct AwsScraper;
+
+impl WindSource for AwsScraper {
+    fn name(&self) -> &'static str {
+        "aws-scraper"
+    }
+
+    fn fetch(&self) -> Result<(Vec<Observation>, i64), String> {
+        reqwest::get(AWS_PAGE_URL)
+            .and_then(|mut res| res.text())
+            .map_err(|err| err.to_string())
+            .and_then(|html| parse_html(&html))
+    }
+}
+
+
+/// Open data API returning the `response.body.items.item` of data.go.kr in JSON or XML.
+/// `{key}` in the URL is replaced with the API key.
+pub struct OpenDataApi {
+    url: String,
+    api_key: String,
+}
+
+impl OpenDataApi {
+    pub fn new(url: String, api_key: String) -> Self {
+        OpenDataApi {
+            url,
+            api_key,
+        }
+    }
+}
+
+impl WindSource for OpenDataApi {
+    fn name(&self) -> &'static str {
+        "open-data-api"
+    }
+
+    fn fetch(&self) -> Result<(Vec<Observation>, i64), String> {
+        reqwest::get(&self.url.replace("{key}", &self.api_key))
+            .and_then(|mut res| res.text())
+            .map_err(|err| err.to_string())
+            .and_then(|text| parse_api_response(&text))
+    }
+}
+
+
+/// Read a saved AWS page or API response so it works offline.
+/// The file is parsed as the API response if its extension is `json` or `xml`.
+pub struct FileSource {
+    path: String,
+}
+
+impl FileSource {
+    pub fn new(path: String) -> Self {
+        FileSource {
+            path,
+        }
+    }
+}
+
+impl WindSource for FileSource {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn fetch(&self) -> Result<(Vec<Observation>, i64), String> {
+        let text = fs::read_to_string(&self.path)
+            .map_err(|err| err.to_string())?;
+
+        let ext = Path::new(&self.path).extension().and_then(|ext| ext.to_str());
+        if ext == Some("json") || ext == Some("xml") {
+            parse_api_response(&text)
+        }
+        else {
+            parse_html(&text)
+        }
+    }
+}
+
+
+/// Try sources in order until one of them succeeds.
+pub struct FallbackSource {
+    sources: Vec<Box<dyn WindSource>>,
+}
+
+impl WindSource for FallbackSource {
+    fn name(&self) -> &'static str {
+        "fallback"
+    }
+
+    fn fetch(&self) -> Result<(Vec<Observation>, i64), String> {
+        let mut errors = Vec::new();
+
+        for source in &self.sources {
+            match source.fetch() {
+                Ok(result) => return Ok(result),
+                Err(err) => {
+                    warn!("Fail to fetch from {}: {}", source.name(), err);
+                    errors.push(format!("{}: {}", source.name(), err));
+                },
+            }
+        }
+
+        Err(errors.join(", "))
+    }
+}
+
+
+/// Build the source from `WIND_SOURCE_FILE`, `WIND_API_URL` and `WIND_API_KEY`.
+/// The API is tried before the scraper when it is configured.
+pub fn from_env() -> Box<dyn WindSource> {
+    if let Ok(path) = env::var("WIND_SOURCE_FILE") {
+        info!("Use wind source file: {}", path);
+        return Box::new(FileSource::new(path));
+    }
+
+    let mut sources: Vec<Box<dyn WindSource>> = Vec::new();
+
+    match (env::var("WIND_API_URL"), env::var("WIND_API_KEY")) {
+        (Ok(url), Ok(api_key)) => sources.push(Box::new(OpenDataApi::new(url, api_key))),
+        (Ok(_), Err(_)) => warn!("WIND_API_URL is set without WIND_API_KEY"),
+        _ => (),
+    }
+
+    sources.push(Box::new(AwsScraper));
+
+    if sources.len() == 1 {
+        sources.pop().unwrap()
+    }
+    else {
+        Box::new(FallbackSource { sources })
+    }
+}
+
+
+fn parse_html(html: &str) -> Result<(Vec<Observation>, i64), String> {
+    let observed_time = aws_parser::parse_observed_time(html).unwrap_or_else(|| {
+        warn!("Fail to find wind observation time");
+        util::current_minute()
+    });
+
+    aws_parser::parse_observations(html)
+        .map(|observations| (observations, observed_time))
+        .map_err(|err| err.to_string())
+}
+
+/// Fields of an API item as they are in the response.
+type ApiItem = HashMap<String, String>;
+
+/// The API answers in XML or JSON depending on its `dataType` parameter.
+fn parse_api_response(text: &str) -> Result<(Vec<Observation>, i64), String> {
+    let items = if text.trim_start().starts_with('<') {
+        parse_api_xml(text)?
+    }
+    else {
+        parse_api_json(text)?
+    };
+
+    parse_api_items(&items)
+}
+
+fn parse_api_json(text: &str) -> Result<Vec<ApiItem>, String> {
+    let data: JsonValue = serde_json::from_str(text)
+        .map_err(|err| err.to_string())?;
+
+    let header = &data["response"]["header"];
+    if let Some(code) = header["resultCode"].as_str().filter(|&code| code != "00") {
+        return Err(format!("API error {}: {}", code, header["resultMsg"].as_str().unwrap_or("")));
+    }
+
+    let items = &data["response"]["body"]["items"]["item"];
+    let items = match items {
+        JsonValue::Array(arr) => arr.iter().collect::<Vec<_>>(),
+        JsonValue::Object(_) => vec![items],
+        _ => return Err("Can't find items".into()),
+    };
+
+    Ok(items.into_iter()
+        .filter_map(|item| item.as_object())
+        .map(|item| {
+            item.iter()
+                .filter_map(|(key, value)| {
+                    // The API gives numbers as strings but accept real numbers too.
+                    match value {
+                        JsonValue::String(s) => Some((key.clone(), s.clone())),
+                        JsonValue::Number(n) => Some((key.clone(), n.to_string())),
+                        _ => None,
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn parse_api_xml(text: &str) -> Result<Vec<ApiItem>, String> {
+    let mut reader = xml::Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut name = Vec::new();
+    let mut item: Option<ApiItem> = None;
+    let mut items = Vec::new();
+    let mut result_code = None;
+    let mut result_msg = String::new();
+
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                name.clear();
+                name.extend_from_slice(e.name());
+
+                if e.name() == b"item" {
+                    item = Some(ApiItem::new());
+                }
+            },
+            Ok(Event::End(ref e)) => {
+                if e.name() == b"item" {
+                    items.extend(item.take());
+                }
+                name.clear();
+            },
+            Ok(Event::Text(e)) => {
+                let text = e.unescape_and_decode(&reader).map_err(|err| err.to_string())?;
+
+                match (name.as_slice(), item.as_mut()) {
+                    (b"resultCode", _) => result_code = Some(text),
+                    (b"resultMsg", _) => result_msg = text,
+                    (_, Some(item)) if !name.is_empty() => {
+                        item.insert(String::from_utf8_lossy(&name).into_owned(), text);
+                    },
+                    _ => (),
+                }
+            },
+            Ok(Event::Eof) => break,
+            Err(err) => return Err(err.to_string()),
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    match result_code {
+        Some(code) if code != "00" => Err(format!("API error {}: {}", code, result_msg)),
+        Some(_) => Ok(items),
+        None => Err("Can't find the result code".into()),
+    }
+}
+
+fn parse_api_items(items: &[ApiItem]) -> Result<(Vec<Observation>, i64), String> {
+    let kst = util::kst();
+    let mut observed_time = None;
+    let mut observations = Vec::with_capacity(items.len());
+
+    for item in items {
+        let stn_id = match item.get("stnId").map(|id| id.trim()).filter(|id| !id.is_empty()) {
+            Some(id) => id.to_owned(),
+            None => continue,
+        };
+
+        let time = item.get("tm")
+            .and_then(|tm| kst.datetime_from_str(tm.trim(), "%Y-%m-%d %H:%M").ok())
+            .map(|time| time.timestamp());
+        if let Some(time) = time {
+            observed_time = observed_time.max(Some(time));
+        }
+
+        observations.push(Observation {
+            stn_id,
+            wind_dir: item_f64(item, "wd"),
+            wind_speed: item_f64(item, "ws"),
+            temperature: item_f64(item, "ta"),
+            humidity: item_f64(item, "hm"),
+            precipitation: item_f64(item, "rn"),
+        });
+    }
+
+    if observations.is_empty() {
+        return Err("There is no observation in the response".into());
+    }
+
+    Ok((observations, observed_time.unwrap_or_else(util::current_minute)))
+}
+
+/// Missing values are given as an empty string.
+fn item_f64(item: &ApiItem, key: &str) -> Option<f64> {
+    item.get(key)
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|val: &f64| val.is_finite())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    const API_JSON: &str = include_str!("../tests/fixtures/aws_api.json");
+    const API_XML: &str = include_str!("../tests/fixtures/aws_api.xml");
+    const OBSERVED_TIME: i64 = 1554441900; // 2019-04-05 14:25 KST
+
+    fn fixture_path(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn find_observation<'a>(observations: &'a [Observation], stn_id: &str) -> &'a Observation {
+        observations.iter().find(|obs| obs.stn_id == stn_id).unwrap()
+    }
+
+    fn check_api_observations(observations: &[Observation], observed_time: i64) {
+        assert_eq!(observed_time, OBSERVED_TIME);
+        assert_eq!(observations.len(), 3);
+
+        let sokcho = find_observation(observations, "90");
+        assert_eq!(sokcho.wind_dir, Some(270.5));
+        assert_eq!(sokcho.wind_speed, Some(6.2));
+        assert_eq!(sokcho.temperature, Some(14.1));
+        assert_eq!(sokcho.humidity, Some(18.0));
+        assert_eq!(sokcho.precipitation, Some(0.0));
+
+        let daegwallyeong = find_observation(observations, "93");
+        assert_eq!(daegwallyeong.wind_dir, Some(225.0));
+        assert_eq!(daegwallyeong.wind_speed, Some(3.4));
+
+        // Missing values are empty.
+        let cheorwon = find_observation(observations, "95");
+        assert_eq!(cheorwon.wind_dir, None);
+        assert_eq!(cheorwon.wind_speed, None);
+        assert_eq!(cheorwon.temperature, Some(15.0));
+        assert_eq!(cheorwon.humidity, None);
+    }
+
+    /// Source returning `stations` observations or an error, counting its calls.
+    struct FakeSource {
+        name: &'static str,
+        stations: Option<usize>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl FakeSource {
+        fn boxed(name: &'static str, stations: Option<usize>) -> (Box<dyn WindSource>, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let source = FakeSource {
+                name,
+                stations,
+                calls: calls.clone(),
+            };
+
+            (Box::new(source), calls)
+        }
+    }
+
+    impl WindSource for FakeSource {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn fetch(&self) -> Result<(Vec<Observation>, i64), String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+
+            let stations = self.stations.ok_or_else(|| "unavailable".to_owned())?;
+            let observations = (0..stations)
+                .map(|idx| Observation {
+                    stn_id: format!("{}-{}", self.name, idx),
+                    wind_dir: Some(0.0),
+                    wind_speed: Some(1.0),
+                    temperature: None,
+                    humidity: None,
+                    precipitation: None,
+                })
+                .collect();
+
+            Ok((observations, OBSERVED_TIME))
+        }
+    }
+
+    #[test]
+    fn parse_api_json_response() {
+        let (observations, observed_time) = parse_api_response(API_JSON).unwrap();
+        check_api_observations(&observations, observed_time);
+    }
+
+    #[test]
+    fn parse_api_xml_response() {
+        let (observations, observed_time) = parse_api_response(API_XML).unwrap();
+        check_api_observations(&observations, observed_time);
+    }
+
+    #[test]
+    fn api_error_code_is_error() {
+        let json = r#"{"response":{"header":{"resultCode":"30","resultMsg":"SERVICE_KEY_IS_NOT_REGISTERED_ERROR"}}}"#;
+        let err = parse_api_response(json).unwrap_err();
+        assert!(err.contains("30"), "{}", err);
+
+        let xml = "<response><header><resultCode>30</resultCode>\
+            <resultMsg>SERVICE_KEY_IS_NOT_REGISTERED_ERROR</resultMsg></header></response>";
+        let err = parse_api_response(xml).unwrap_err();
+        assert!(err.contains("SERVICE_KEY_IS_NOT_REGISTERED_ERROR"), "{}", err);
+
+        // An empty body is not a successful fetch.
+        let xml = "<response><header><resultCode>00</resultCode></header><body><items></items></body></response>";
+        assert!(parse_api_response(xml).is_err());
+    }
+
+    #[test]
+    fn file_source_reads_api_and_html() {
+        let (observations, observed_time) = FileSource::new(fixture_path("aws_api.json")).fetch().unwrap();
+        check_api_observations(&observations, observed_time);
+
+        let (observations, observed_time) = FileSource::new(fixture_path("aws_api.xml")).fetch().unwrap();
+        check_api_observations(&observations, observed_time);
+
+        let (observations, observed_time) = FileSource::new(fixture_path("aws.html")).fetch().unwrap();
+        assert_eq!(observed_time, OBSERVED_TIME);
+        assert_eq!(observations.len(), 4);
+        assert_eq!(find_observation(&observations, "90").wind_speed, Some(7.8));
+
+        assert!(FileSource::new(fixture_path("missing.html")).fetch().is_err());
+    }
+
+    #[test]
+    fn fallback_stops_at_first_success() {
+        let (first, first_calls) = FakeSource::boxed("first", None);
+        let (second, second_calls) = FakeSource::boxed("second", Some(2));
+        let (third, third_calls) = FakeSource::boxed("third", Some(3));
+
+        let source = FallbackSource {
+            sources: vec![first, second, third],
+        };
+
+        let (observations, _) = source.fetch().unwrap();
+        assert_eq!(observations.len(), 2);
+        assert_eq!(observations[0].stn_id, "second-0");
+
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(third_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn fallback_prefers_first_source() {
+        let (first, first_calls) = FakeSource::boxed("first", Some(1));
+        let (second, second_calls) = FakeSource::boxed("second", Some(2));
+
+        let source = FallbackSource {
+            sources: vec![first, second],
+        };
+
+        let (observations, _) = source.fetch().unwrap();
+        assert_eq!(observations[0].stn_id, "first-0");
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn fallback_reports_all_errors_in_order() {
+        let (first, _) = FakeSource::boxed("first", None);
+        let (second, _) = FakeSource::boxed("second", None);
+
+        let source = FallbackSource {
+            sources: vec![first, second],
+        };
+
+        assert_eq!(source.fetch().unwrap_err(), "first: unavailable, second: unavailable");
+    }
+}
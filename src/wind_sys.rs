@@ -1,27 +1,38 @@
 use std::{
+    env,
     f64,
     fs::File,
-    io::{self, BufReader, BufRead, Write},
-    sync::RwLock,
-    time::Duration,
+    io::{self, BufReader, BufRead, Cursor, Write},
+    sync::{Arc, RwLock},
+    time::{UNIX_EPOCH, Duration},
     collections::HashMap,
-    time::Instant,
     rc::Rc,
     cell::RefCell,
     clone::Clone,
 };
 use rocket::{
-    http::ContentType,
+    Request,
+    http::{ContentType, Status},
     response::{
+        self,
         Content,
+        Response,
+        Responder,
         content::Json,
+        status::{BadRequest, Custom},
     },
 };
 use serde_json::json;
-use cgmath::{MetricSpace, Point2, Vector2};
+use chrono::Utc;
+use cgmath::{Point2, Vector2};
 use png::HasParameters;
 
+use crate::db;
 use crate::util;
+use crate::event_sys::{self, Layer};
+use crate::aws_parser::Observation;
+use crate::wind_source::{self, WindSource};
+use crate::wind_field::{self, WindField, InterpolationMethod};
 use crate::task_scheduler::{Task, TaskSchedulerBuilder};
 
 
@@ -46,6 +57,7 @@ lazy_static! {
 
             if let (Ok(lat), Ok(lon)) = (data[5].parse(), data[6].parse()) {
                 map.insert(data[0].to_owned(), Station {
+                    name: data[3].to_owned(),
                     latitude: lat,
                     longitude: lon,
                 });
@@ -60,19 +72,82 @@ lazy_static! {
     static ref WIND_IMG: RwLock<HashMap<u64, Vec<u8>>> = {
         RwLock::new(HashMap::new())
     };
-    static ref CLOCK: Instant = {
-        Instant::now()
+    static ref WIND_GRID: RwLock<HashMap<u64, Arc<WindGrid>>> = {
+        RwLock::new(HashMap::new())
+    };
+    static ref WIND_FRAMES: RwLock<HashMap<u64, Arc<WindFrame>>> = {
+        RwLock::new(HashMap::new())
+    };
+    static ref WIND_TILES: RwLock<HashMap<(u64, u32, u32, u32), Vec<u8>>> = {
+        RwLock::new(HashMap::new())
+    };
+    static ref WEATHER_STATIONS: RwLock<String> = {
+        RwLock::new(json!({
+            "error": true,
+            "stations": [],
+            "size": 0,
+        }).to_string())
+    };
+    static ref HUMIDITY_IMG: RwLock<HashMap<u64, Vec<u8>>> = {
+        RwLock::new(HashMap::new())
+    };
+    static ref WIND_HISTORY: RwLock<HashMap<i64, Arc<HistoryFrame>>> = {
+        RwLock::new(HashMap::new())
+    };
+    static ref WIND_SOURCE: Box<dyn WindSource> = {
+        wind_source::from_env()
+    };
+    static ref WIND_FIELD: RwLock<Option<Arc<WindField>>> = {
+        RwLock::new(None)
+    };
+    static ref GRID: Grid = {
+        // A typo shouldn't silently serve the default area.
+        Grid::from_env().unwrap_or_else(|err| panic!("Invalid wind grid config: {}", err))
+    };
+    static ref INTERPOLATION: InterpolationMethod = {
+        let idw = || {
+            let power = env::var("WIND_IDW_POWER").ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|&power| power > 0.0)
+                .unwrap_or(wind_field::DEFAULT_IDW_POWER);
+            let radius = env::var("WIND_IDW_RADIUS").ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|&radius| radius > 0.0)
+                .map(|radius| radius / GRID.resolution);
+
+            InterpolationMethod::Idw { power, radius }
+        };
+
+        match env::var("WIND_INTERPOLATION") {
+            Ok(ref method) if method == "delaunay" => InterpolationMethod::Delaunay,
+            Ok(ref method) if method == "nearest" => InterpolationMethod::Nearest,
+            Ok(ref method) if method == "idw" => idw(),
+            Ok(method) => {
+                warn!("Unknown wind interpolation method: {}", method);
+                idw()
+            },
+            Err(_) => idw(),
+        }
     };
 }
 
-const GRID_X_OFFSET: f64 = 13955566.87619434;
-const GRID_Y_OFFSET: f64 = 3885936.2337022102;
-const GRID_X_END: f64 = 14493683.55532198;
-const GRID_Y_END: f64 = 4734203.787602952;
-const GRID_RESOLUTION: f64 = 1024.0;
-const GRID_HEIGHT: usize = ((GRID_Y_END - GRID_Y_OFFSET) / GRID_RESOLUTION) as usize;
-const GRID_WIDTH: usize = ((GRID_X_END - GRID_X_OFFSET) / GRID_RESOLUTION) as usize;
-const STATION_RANGE: i32 = 32;
+const DEFAULT_GRID_X_OFFSET: f64 = 13955566.87619434;
+const DEFAULT_GRID_Y_OFFSET: f64 = 3885936.2337022102;
+const DEFAULT_GRID_X_END: f64 = 14493683.55532198;
+const DEFAULT_GRID_Y_END: f64 = 4734203.787602952;
+const DEFAULT_GRID_RESOLUTION: f64 = 1024.0;
+const DEFAULT_STATION_RANGE: f64 = 32.0 * DEFAULT_GRID_RESOLUTION; // meters
+const MAX_GRID_CELLS: usize = 4096 * 4096;
+const MAX_LATITUDE: f64 = 85.0;
+const INTERPOLATION_WORKERS: usize = 4;
+const MAX_JSON_GRID_CELLS: usize = 64 * 64;
+const TILE_SIZE: usize = 256;
+const MAX_TILE_ZOOM: u32 = 12;
+const MAX_TILE_CACHE_SIZE: usize = 2048;
+const WIND_HISTORY_DURATION: u64 = 7 * 24 * 60 * 60; // seconds
+const MAX_HISTORY_HOURS: u64 = 24 * 7;
+const MAX_HISTORY_CACHE_SIZE: usize = 24 * 12;
+const IMG_MAX_AGE: u64 = 60 * 60; // seconds
 
 
 struct ByteVec(Rc<RefCell<Vec<u8>>>);
@@ -108,29 +183,230 @@ impl ByteVec {
 
 
 struct Station {
+    name: String,
     latitude: f64,
     longitude: f64,
 }
 
 
 struct StationData {
+    id: String,
     latitude: f64,
     longitude: f64,
     wind: Vector2<f64>,
 }
 
 
+/// Extent and resolution of the wind grid in web mercator meters.
+struct Grid {
+    x_offset: f64,
+    y_offset: f64,
+    resolution: f64,
+    width: usize,
+    height: usize,
+    station_range: i32, // cells
+}
+
+impl Grid {
+    fn new(x_offset: f64, y_offset: f64, x_end: f64, y_end: f64, resolution: f64, station_range: f64) -> Self {
+        Grid {
+            x_offset,
+            y_offset,
+            resolution,
+            width: ((x_end - x_offset) / resolution) as usize,
+            height: ((y_end - y_offset) / resolution) as usize,
+            station_range: (station_range / resolution).ceil() as i32,
+        }
+    }
+
+    /// The extent is given in degrees and the resolution and station range in meters.
+    /// Unset values keep the default.
+    fn from_env() -> Result<Self, String> {
+        let read = |name: &str| -> Result<Option<f64>, String> {
+            match env::var(name) {
+                Ok(val) => val.parse::<f64>()
+                    .ok()
+                    .filter(|val| val.is_finite())
+                    .map(Some)
+                    .ok_or(format!("{} is not a number", name)),
+                Err(_) => Ok(None),
+            }
+        };
+
+        let extent = [
+            read("WIND_GRID_MIN_LON")?,
+            read("WIND_GRID_MIN_LAT")?,
+            read("WIND_GRID_MAX_LON")?,
+            read("WIND_GRID_MAX_LAT")?,
+        ];
+        let resolution = read("WIND_GRID_RESOLUTION")?.unwrap_or(DEFAULT_GRID_RESOLUTION);
+        let station_range = read("WIND_STATION_RANGE")?.unwrap_or(DEFAULT_STATION_RANGE);
+
+        let (x_offset, y_offset, x_end, y_end) = match extent {
+            [None, None, None, None] => {
+                (DEFAULT_GRID_X_OFFSET, DEFAULT_GRID_Y_OFFSET, DEFAULT_GRID_X_END, DEFAULT_GRID_Y_END)
+            },
+            [Some(min_lon), Some(min_lat), Some(max_lon), Some(max_lat)] => {
+                if min_lon < -180.0 || max_lon > 180.0 || min_lon >= max_lon {
+                    return Err("Longitude range must be in -180~180 and min < max".into());
+                }
+                if min_lat < -MAX_LATITUDE || max_lat > MAX_LATITUDE || min_lat >= max_lat {
+                    return Err(format!("Latitude range must be in -{0}~{0} and min < max", MAX_LATITUDE));
+                }
+
+                let (x_offset, y_offset) = util::transform_lonlat(min_lon, min_lat);
+                let (x_end, y_end) = util::transform_lonlat(max_lon, max_lat);
+
+                (x_offset, y_offset, x_end, y_end)
+            },
+            _ => return Err("All of WIND_GRID_MIN_LON, MIN_LAT, MAX_LON and MAX_LAT are needed".into()),
+        };
+
+        if resolution <= 0.0 {
+            return Err("WIND_GRID_RESOLUTION must be positive".into());
+        }
+        if station_range <= 0.0 {
+            return Err("WIND_STATION_RANGE must be positive".into());
+        }
+
+        let grid = Grid::new(x_offset, y_offset, x_end, y_end, resolution, station_range);
+
+        if grid.width == 0 || grid.height == 0 {
+            return Err("The grid is smaller than a cell".into());
+        }
+        if grid.width * grid.height > MAX_GRID_CELLS {
+            return Err(format!("The grid has {}x{} cells, more than {}", grid.width, grid.height, MAX_GRID_CELLS));
+        }
+
+        Ok(grid)
+    }
+}
+
+
+/// Interpolated wind in float32, row-major from the top row like the image.
+/// It's encoded once as the response body, little-endian u array followed by v array.
+struct WindGrid {
+    bytes: Vec<u8>,
+}
+
+impl WindGrid {
+    fn new(grid: &[Vector2<f64>]) -> Self {
+        let mut u_bytes = Vec::with_capacity(grid.len() * 4);
+        let mut v_bytes = Vec::with_capacity(grid.len() * 4);
+
+        for y in (0..GRID.height).rev() {
+            for wind in &grid[(y * GRID.width)..((y + 1) * GRID.width)] {
+                u_bytes.extend_from_slice(&(wind.x as f32).to_le_bytes());
+                v_bytes.extend_from_slice(&(wind.y as f32).to_le_bytes());
+            }
+        }
+        u_bytes.append(&mut v_bytes);
+
+        WindGrid { bytes: u_bytes }
+    }
+
+    fn cells(&self) -> usize {
+        self.bytes.len() / 8
+    }
+
+    fn value(&self, idx: usize) -> f32 {
+        let mut val = [0; 4];
+        val.copy_from_slice(&self.bytes[(idx * 4)..(idx * 4 + 4)]);
+        f32::from_le_bytes(val)
+    }
+
+    fn u(&self, idx: usize) -> f32 {
+        self.value(idx)
+    }
+
+    fn v(&self, idx: usize) -> f32 {
+        self.value(self.cells() + idx)
+    }
+}
+
+
+/// Body of a stored wind grid, served without copying it.
+pub struct WindGridBody(Arc<WindGrid>);
+
+impl AsRef<[u8]> for WindGridBody {
+    fn as_ref(&self) -> &[u8] {
+        &self.0.bytes
+    }
+}
+
+impl<'r> Responder<'r> for WindGridBody {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .header(ContentType::Binary)
+            .sized_body(Cursor::new(self))
+            .ok()
+    }
+}
+
+
+/// Wind field of a refresh with the velocity range used to normalize its images.
+struct WindFrame {
+    field: Arc<WindField>,
+    min: Vector2<f64>,
+    max: Vector2<f64>,
+    /// Whether each grid cell is in station range, row-major from the bottom row.
+    coverage: Vec<bool>,
+}
+
+
+/// Wind image regenerated from the stored observations.
+struct HistoryFrame {
+    metadata: String,
+    img: Vec<u8>,
+}
+
+
+/// PNG of an immutable wind image which clients can cache by its id.
+pub struct CachedPng {
+    etag: String,
+    img: Vec<u8>,
+}
+
+impl<'r> Responder<'r> for CachedPng {
+    fn respond_to(self, req: &Request) -> response::Result<'r> {
+        if req.headers().get("If-None-Match").any(|tag| tag == self.etag || tag == "*") {
+            return Response::build()
+                .status(Status::NotModified)
+                .raw_header("ETag", self.etag)
+                .ok();
+        }
+
+        Response::build_from(Content(ContentType::PNG, self.img).respond_to(req)?)
+            .raw_header("Cache-Control", format!("public, max-age={}, immutable", IMG_MAX_AGE))
+            .raw_header("ETag", self.etag)
+            .ok()
+    }
+}
+
+
+struct WindMap {
+    id: u64,
+    metadata: String,
+    img: Vec<u8>,
+    grid: Option<WindGrid>,
+    frame: Option<Arc<WindFrame>>,
+}
+
+
 pub fn init_wind_sys(scheduler: &mut TaskSchedulerBuilder) {
+    info!("Wind grid {}x{} in {}m", GRID.width, GRID.height, GRID.resolution);
+
     let delay = match get_wind_img() {
-        Ok((id, metadata, img)) => {
-            update_wind_map(id, metadata, img);
+        Ok(Some(wind_map)) => {
+            update_wind_map(wind_map);
             Duration::new(60 * 5, 0)
         },
-        Err(err) => {
-            warn!("Fail to init wind: {}", err);
+        result => {
+            if let Err(err) = result {
+                warn!("Fail to init wind: {}", err);
+            }
 
-            let (id, metadata, img) = make_error_response();
-            update_wind_map(id, metadata, img);
+            update_wind_map(make_error_response());
 
             Duration::new(60 * 1, 0)
         }
@@ -144,26 +420,214 @@ pub fn get_wind_map_metadata() -> Json<String> {
     Json(WIND_METADATA.read().unwrap().clone())
 }
 
+/// Image of `id`, which is the observation time so it never changes.
 #[get("/wind-map?<id>")]
-pub fn get_wind_map(id: u64) -> Option<Content<Vec<u8>>> {
+pub fn get_wind_map(id: u64) -> Option<CachedPng> {
     let map = WIND_IMG.read().unwrap();
     if let Some(img) = map.get(&id) {
-        Some(Content(ContentType::PNG, img.clone()))
+        Some(CachedPng {
+            etag: format!("\"wind-{}\"", id),
+            img: img.clone(),
+        })
     }
     else {
         None
     }
 }
 
+/// Latest observations of every known station.
+#[get("/weather-stations")]
+pub fn get_weather_stations() -> Json<String> {
+    Json(WEATHER_STATIONS.read().unwrap().clone())
+}
+
+/// Relative humidity image of `id` in the wind grid, 0~100% scaled to 0~255 in red.
+#[get("/humidity-map?<id>")]
+pub fn get_humidity_map(id: u64) -> Option<CachedPng> {
+    let map = HUMIDITY_IMG.read().unwrap();
+    map.get(&id).map(|img| CachedPng {
+        etag: format!("\"humidity-{}\"", id),
+        img: img.clone(),
+    })
+}
+
+#[get("/wind-grid?<id>")]
+pub fn get_wind_grid(id: u64) -> Option<WindGridBody> {
+    let grid = WIND_GRID.read().unwrap().get(&id).cloned();
+    grid.map(WindGridBody)
+}
+
+#[get("/wind-grid-json?<id>&<min_lat>&<min_lon>&<max_lat>&<max_lon>")]
+pub fn get_wind_grid_json(id: u64, min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64)
+    -> Result<Json<String>, Custom<String>> {
+
+    let grid = WIND_GRID.read().unwrap().get(&id).cloned()
+        .ok_or(Custom(Status::NotFound, "There is no such wind grid".into()))?;
+
+    let (min_x, min_y) = util::transform_lonlat(min_lon, min_lat);
+    let (max_x, max_y) = util::transform_lonlat(max_lon, max_lat);
+
+    // Every cell touching the bbox, so a bbox inside a cell still gets it.
+    let to_cell = |v: f64, offset: f64, size: usize, round: fn(f64) -> f64| {
+        0_f64.max(round((v - offset) / GRID.resolution)).min(size as f64) as usize
+    };
+    let begin_x = to_cell(min_x, GRID.x_offset, GRID.width, f64::floor);
+    let end_x = to_cell(max_x, GRID.x_offset, GRID.width, f64::ceil);
+    let begin_y = to_cell(min_y, GRID.y_offset, GRID.height, f64::floor);
+    let end_y = to_cell(max_y, GRID.y_offset, GRID.height, f64::ceil);
+
+    if begin_x >= end_x || begin_y >= end_y {
+        return Err(Custom(Status::BadRequest, "The bbox is out of the grid".into()));
+    }
+
+    let (width, height) = (end_x - begin_x, end_y - begin_y);
+
+    if width * height > MAX_JSON_GRID_CELLS {
+        return Err(Custom(Status::BadRequest, format!("The bbox can not contain more than {} cells", MAX_JSON_GRID_CELLS)));
+    }
+
+    let mut u = Vec::with_capacity(width * height);
+    let mut v = Vec::with_capacity(width * height);
+
+    // From the top row like the full grid.
+    for y in (begin_y..end_y).rev() {
+        let row = (GRID.height - 1 - y) * GRID.width;
+        u.extend((begin_x..end_x).map(|x| grid.u(row + x)));
+        v.extend((begin_x..end_x).map(|x| grid.v(row + x)));
+    }
+
+    Ok(Json(json!({
+        "id": id,
+        "x": begin_x,
+        "y": begin_y,
+        "width": width,
+        "height": height,
+        "u": u,
+        "v": v,
+    }).to_string()))
+}
+
+/// Observation times of the stored wind history.
+#[get("/wind-map-history?<hours>")]
+pub fn get_wind_map_history(hours: Option<u64>) -> Result<Json<String>, BadRequest<String>> {
+    let hours = hours.unwrap_or(24).min(MAX_HISTORY_HOURS);
+    let since = util::current_time()
+        - Duration::new(hours * 60 * 60, 0);
+
+    db::get_wind_observation_times(since)
+        .map(|times| {
+            let frames = times.iter()
+                .map(|&time| util::to_secs(time))
+                .collect::<Vec<_>>();
+
+            Json(json!({
+                "frames": frames,
+                "size": frames.len(),
+            }).to_string())
+        })
+        .map_err(|err| BadRequest(Some(err.to_string())))
+}
+
+#[get("/wind-map-history-metadata?<time>")]
+pub fn get_wind_map_history_metadata(time: u64) -> Result<Json<String>, Custom<String>> {
+    get_history_frame(time)
+        .map(|frame| Json(frame.metadata.clone()))
+}
+
+#[get("/wind-map-history-img?<time>")]
+pub fn get_wind_map_history_img(time: u64) -> Result<Content<Vec<u8>>, Custom<String>> {
+    get_history_frame(time)
+        .map(|frame| Content(ContentType::PNG, frame.img.clone()))
+}
+
+/// Web mercator tile of the wind image of `id`.
+/// `y` is like `123.png`. Tiles out of the wind grid are not found.
+#[get("/wind-tiles/<z>/<x>/<y>?<id>")]
+pub fn get_wind_tile(z: u32, x: u32, y: String, id: u64) -> Option<CachedPng> {
+    let y = y.trim_end_matches(".png").parse::<u32>().ok()?;
+
+    if z > MAX_TILE_ZOOM || x >= (1 << z) || y >= (1 << z) || !is_tile_in_grid(z, x, y) {
+        return None;
+    }
+
+    let key = (id, z, x, y);
+    let etag = format!("\"wind-{}-{}-{}-{}\"", id, z, x, y);
+
+    if let Some(tile) = WIND_TILES.read().unwrap().get(&key) {
+        return Some(CachedPng { etag, img: tile.clone() });
+    }
+
+    let frame = WIND_FRAMES.read().unwrap().get(&id).cloned()?;
+
+    match make_wind_tile(&frame, z, x, y) {
+        Ok(tile) => {
+            let mut cache = WIND_TILES.write().unwrap();
+
+            if cache.len() >= MAX_TILE_CACHE_SIZE {
+                cache.clear();
+            }
+
+            cache.insert(key, tile.clone());
+
+            Some(CachedPng { etag, img: tile })
+        },
+        Err(err) => {
+            warn!("Fail to make wind tile: {}", err);
+            None
+        },
+    }
+}
+
+/// Interpolated wind at a point.
+/// `u` and `v` are the eastward and northward wind components and `dir` is where the wind comes from in degrees like the AWS data.
+#[get("/wind?<lat>&<lon>")]
+pub fn get_wind(lat: f64, lon: f64) -> Result<Json<String>, Custom<String>> {
+    let point = to_wind_grid_point(lat, lon)
+        .ok_or(Custom(Status::BadRequest, "The point is out of the wind grid".into()))?;
+
+    let field = WIND_FIELD.read().unwrap();
+    let field = field.as_ref()
+        .ok_or(Custom(Status::NotFound, "There is no wind data".into()))?;
+
+    // Station vectors point where the wind comes from.
+    let (wind, station_cnt) = field.interpolate_with_count(point);
+    let speed = wind.x.hypot(wind.y);
+    let direction = wind.x.atan2(wind.y).to_degrees();
+    let direction = if direction < 0.0 { direction + 360.0 } else { direction };
+
+    Ok(Json(json!({
+        "u": -wind.x,
+        "v": -wind.y,
+        "speed": speed,
+        "dir": direction,
+        "stations": station_cnt,
+        "time": field.observed_time,
+    }).to_string()))
+}
+
+
+/// Interpolated wind and its observation time.
+/// The vector points where the wind comes from like the station data.
+pub fn get_wind_at(latitude: f64, longitude: f64) -> Option<(Vector2<f64>, i64)> {
+    let point = to_wind_grid_point(latitude, longitude)?;
+    let field = WIND_FIELD.read().unwrap().clone()?;
+
+    Some((field.interpolate(point), field.observed_time))
+}
+
 
 fn wind_job() -> Duration {
     info!("Start job");
 
     match get_wind_img() {
-        Ok((id, metadata, img)) => {
-            update_wind_map(id, metadata, img);
+        Ok(Some(wind_map)) => {
+            update_wind_map(wind_map);
             Duration::new(60 * 5, 0)
         },
+        Ok(None) => {
+            info!("Wind data is not updated yet");
+            Duration::new(60 * 1, 0)
+        },
         Err(err) => {
             warn!("Fail to get wind image: {}", err);
             Duration::new(60 * 1, 0)
@@ -171,265 +635,490 @@ fn wind_job() -> Duration {
     }
 }
 
-fn update_wind_map(id: u64, metadata: String, wind_img: Vec<u8>) {
+fn update_wind_map(wind_map: WindMap) {
     {
         let mut map = WIND_IMG.write().unwrap();
 
         // Remove old image data.
-        let current_secs = CLOCK.elapsed().as_secs();
-        map.retain(|&time, _| current_secs < time + 60 * 60);
+        let current_secs = Utc::now().timestamp() as u64;
+        map.retain(|&time, _| current_secs < time + IMG_MAX_AGE);
 
-        map.insert(id, wind_img);
+        map.insert(wind_map.id, wind_map.img);
+    }
+    if let Some(grid) = wind_map.grid {
+        let mut map = WIND_GRID.write().unwrap();
+
+        let current_secs = Utc::now().timestamp() as u64;
+        map.retain(|&time, _| current_secs < time + IMG_MAX_AGE);
+
+        map.insert(wind_map.id, Arc::new(grid));
     }
     {
-        *WIND_METADATA.write().unwrap() = metadata;
+        event_sys::update_cache(&WIND_METADATA, wind_map.metadata, Layer::Wind);
+    }
+    if let Some(frame) = wind_map.frame {
+        *WIND_FIELD.write().unwrap() = Some(frame.field.clone());
+
+        let mut map = WIND_FRAMES.write().unwrap();
+
+        let current_secs = Utc::now().timestamp() as u64;
+        map.retain(|&time, _| current_secs < time + IMG_MAX_AGE);
+
+        map.insert(wind_map.id, frame);
+
+        // Drop tiles of removed frames.
+        WIND_TILES.write().unwrap().retain(|key, _| map.contains_key(&key.0));
     }
 }
 
-fn make_error_response() -> (u64, String, Vec<u8>) {
-    let img_id = CLOCK.elapsed().as_secs();
+fn make_error_response() -> WindMap {
+    let img_id = Utc::now().timestamp() as u64;
 
     let metadata = json!({
         "error": true,
         "id": img_id,
-        "width": GRID_WIDTH,
-        "height": GRID_HEIGHT,
-        "resolution": GRID_RESOLUTION,
-        "offset_x": GRID_X_OFFSET,
-        "offset_y": GRID_Y_OFFSET,
+        "width": GRID.width,
+        "height": GRID.height,
+        "resolution": GRID.resolution,
+        "offset_x": GRID.x_offset,
+        "offset_y": GRID.y_offset,
+        "interpolation": INTERPOLATION.to_json(GRID.resolution),
     }).to_string();
 
-    (img_id, metadata, Vec::new())
+    WindMap {
+        id: img_id,
+        metadata,
+        img: Vec::new(),
+        grid: None,
+        frame: None,
+    }
 }
 
-fn get_wind_img() -> Result<(u64, String, Vec<u8>), String> {
-    get_stations()
-        .and_then(|stations| {
+/// `None` if the observation of the current image is not updated yet.
+fn get_wind_img() -> Result<Option<WindMap>, String> {
+    WIND_SOURCE.fetch()
+        .and_then(|(observations, observed_time)| {
+            let stations = make_wind_stations(&observations);
+
             if stations.is_empty() {
                 warn!("No wind stations");
-                return Ok(make_error_response());
+                return Ok(Some(make_error_response()));
             }
 
+            if WIND_FRAMES.read().unwrap().contains_key(&(observed_time as u64)) {
+                return Ok(None);
+            }
 
-            let mut pixels = Vec::with_capacity(GRID_HEIGHT * GRID_WIDTH * 4);
-            pixels.resize(pixels.capacity(), 0);
+            update_weather(&observations, observed_time);
+            save_wind_observations(&stations, observed_time);
 
+            make_wind_map(stations, observed_time).map(Some)
+        })
+}
 
-            let mut min_x = f64::MAX;
-            let mut min_y = f64::MAX;
-            let mut max_x = f64::MIN;
-            let mut max_y = f64::MIN;
+fn make_wind_map(stations: Vec<StationData>, observed_time: i64) -> Result<WindMap, String> {
+    if stations.is_empty() {
+        return Err("No wind stations".into());
+    }
 
-            let mut station_data = Vec::new();
-            
-            for stn in stations {
-                // Calculate range of wind velocity.
-                if stn.wind.x < min_x {
-                    min_x = stn.wind.x;
-                }
-                if stn.wind.y < min_y {
-                    min_y = stn.wind.y;
-                }
-                if stn.wind.x > max_x {
-                    max_x = stn.wind.x;
-                }
-                if stn.wind.y > max_y {
-                    max_y = stn.wind.y;
-                }
+    let img_id = observed_time as u64;
 
-                // Add stations to delaunay.
-                let (x, y) = util::transform_lonlat(stn.longitude, stn.latitude);
-                let (x, y) = ((x - GRID_X_OFFSET) / GRID_RESOLUTION, (y - GRID_Y_OFFSET) / GRID_RESOLUTION);
-                station_data.push((x, y, stn.wind));
 
-                // Show pixels in station range.
-                for py in (y as i32 - STATION_RANGE)..(y as i32 + STATION_RANGE) {
-                    if py < 0 || py as usize >= GRID_HEIGHT {
-                        continue;
-                    }
+    let mut pixels = Vec::with_capacity(GRID.height * GRID.width * 4);
+    pixels.resize(pixels.capacity(), 0);
 
-                    let y_index = (GRID_HEIGHT - 1 - py as usize) * GRID_WIDTH * 4;
 
-                    for px in (x as i32 - STATION_RANGE)..(x as i32 + STATION_RANGE) {
-                        if px < 0 || px as usize >= GRID_WIDTH {
-                            continue;
-                        }
+    let mut min_x = f64::MAX;
+    let mut min_y = f64::MAX;
+    let mut max_x = f64::MIN;
+    let mut max_y = f64::MIN;
 
-                        let index = y_index + px as usize * 4;
+    let mut station_data = Vec::new();
+    
+    for stn in stations {
+        // Calculate range of wind velocity.
+        if stn.wind.x < min_x {
+            min_x = stn.wind.x;
+        }
+        if stn.wind.y < min_y {
+            min_y = stn.wind.y;
+        }
+        if stn.wind.x > max_x {
+            max_x = stn.wind.x;
+        }
+        if stn.wind.y > max_y {
+            max_y = stn.wind.y;
+        }
 
-                        // Set alpha.
-                        pixels[index + 3] = 255;
-                    }
-                }
-            }
+        // Add stations to delaunay.
+        let (x, y) = to_grid_point(stn.latitude, stn.longitude);
+        station_data.push((x, y, stn.wind));
 
-            let x_term = max_x - min_x;
-            let y_term = max_y - min_y;
+        show_station_range(&mut pixels, x, y);
+    }
 
+    let x_term = max_x - min_x;
+    let y_term = max_y - min_y;
 
-            for y in 0..GRID_HEIGHT {
-                let mut index = (GRID_HEIGHT - 1 - y) * GRID_WIDTH * 4;
+    let field = Arc::new(WindField::new(station_data, observed_time,
+        INTERPOLATION.clone()));
+    let grid = wind_field::interpolate_grid(&field, GRID.width, GRID.height, INTERPOLATION_WORKERS);
 
-                for x in 0..GRID_WIDTH {
-                    let point = Point2::new(x as f64, y as f64);
 
-                    let (total_weight, total_wind_x, total_wind_y) = station_data
-                        .iter()
-                        .map(|(stn_x, stn_y, wind)| {
-                            let distance = point.distance2(Point2::new(*stn_x, *stn_y));
-                            let weight = if distance < 1.0 {
-                                1.0
-                            } else {
-                                1.0 / distance.powf(1.5)
-                            };
-                            (weight, weight * wind)
-                        })
-                        .fold((0.0, 0.0, 0.0), |acc, (weight, wind)| (acc.0 + weight, acc.1 + wind.x, acc.2 + wind.y));
+    for y in 0..GRID.height {
+        let mut index = (GRID.height - 1 - y) * GRID.width * 4;
 
-                    let wind_x = total_wind_x / total_weight;
-                    let wind_x = min_x.max(max_x.min(wind_x));
-                    
-                    let wind_y = total_wind_y / total_weight;
-                    let wind_y = min_y.max(max_y.min(wind_y));
+        for x in 0..GRID.width {
+            let wind = grid[y * GRID.width + x];
 
-                    let norm_wind_x = 255.0 * (wind_x - min_x) / x_term;
-                    let norm_wind_y = 255.0 * (wind_y - min_y) / y_term;
+            let wind_x = min_x.max(max_x.min(wind.x));
+            let wind_y = min_y.max(max_y.min(wind.y));
 
-                    // RGBA
-                    pixels[index + 0] = 0_f64.max(norm_wind_x.floor().min(255.0)) as u8;
-                    pixels[index + 1] = 0_f64.max(norm_wind_y.floor().min(255.0)) as u8;
+            let norm_wind_x = 255.0 * (wind_x - min_x) / x_term;
+            let norm_wind_y = 255.0 * (wind_y - min_y) / y_term;
 
-                    index += 4;
-                }
-            }
+            // RGBA
+            pixels[index + 0] = 0_f64.max(norm_wind_x.floor().min(255.0)) as u8;
+            pixels[index + 1] = 0_f64.max(norm_wind_y.floor().min(255.0)) as u8;
 
-            let img_data = ByteVec::new();
+            index += 4;
+        }
+    }
 
-            let mut encoder = png::Encoder::new(img_data.clone(), GRID_WIDTH as u32, GRID_HEIGHT as u32);
-            encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
-            let img_result = encoder.write_header()
-                .and_then(|mut writer| writer.write_image_data(&pixels));
+    let img_bytes = encode_png(&pixels, GRID.width, GRID.height)?;
 
-            if let Err(err) = img_result {
-                return Err(err.to_string());
-            }
-            
-
-            let img_id = CLOCK.elapsed().as_secs();
-
-            let metadata = json!({
-                "error": false,
-                "id": img_id,
-                "width": GRID_WIDTH,
-                "height": GRID_HEIGHT,
-                "resolution": GRID_RESOLUTION,
-                "offset_x": GRID_X_OFFSET,
-                "offset_y": GRID_Y_OFFSET,
-                "min_x": min_x,
-                "min_y": min_y,
-                "max_x": max_x,
-                "max_y": max_y,
-            }).to_string();
-
-
-            match img_data.bytes() {
-                Ok(bytes) => Ok((img_id, metadata, bytes)),
-                Err(_) => Err("Fail to get image bytes".into()),
+    let mut coverage = Vec::with_capacity(GRID.width * GRID.height);
+    for y in 0..GRID.height {
+        let row = (GRID.height - 1 - y) * GRID.width;
+        coverage.extend((row..(row + GRID.width)).map(|index| pixels[index * 4 + 3] != 0));
+    }
+
+    let metadata = json!({
+        "error": false,
+        "id": img_id,
+        "time": observed_time,
+        "width": GRID.width,
+        "height": GRID.height,
+        "resolution": GRID.resolution,
+        "offset_x": GRID.x_offset,
+        "offset_y": GRID.y_offset,
+        "interpolation": field.method.to_json(GRID.resolution),
+        "grid_id": img_id,
+        "max_tile_zoom": MAX_TILE_ZOOM,
+        "min_x": min_x,
+        "min_y": min_y,
+        "max_x": max_x,
+        "max_y": max_y,
+    }).to_string();
+
+
+    Ok(WindMap {
+        id: img_id,
+        metadata,
+        img: img_bytes,
+        grid: Some(WindGrid::new(&grid)),
+        frame: Some(Arc::new(WindFrame {
+            field,
+            min: Vector2::new(min_x, min_y),
+            max: Vector2::new(max_x, max_y),
+            coverage,
+        })),
+    })
+}
+
+fn to_grid_point(latitude: f64, longitude: f64) -> (f64, f64) {
+    let (x, y) = util::transform_lonlat(longitude, latitude);
+    ((x - GRID.x_offset) / GRID.resolution, (y - GRID.y_offset) / GRID.resolution)
+}
+
+/// Grid point of a coordinate if it's valid and in the grid.
+fn to_wind_grid_point(latitude: f64, longitude: f64) -> Option<Point2<f64>> {
+    if !latitude.is_finite() || !longitude.is_finite() || latitude.abs() > 90.0 || longitude.abs() > 180.0 {
+        return None;
+    }
+
+    let (x, y) = to_grid_point(latitude, longitude);
+
+    if x < 0.0 || y < 0.0 || x > GRID.width as f64 || y > GRID.height as f64 {
+        return None;
+    }
+
+    Some(Point2::new(x, y))
+}
+
+/// Set alpha of pixels in station range.
+fn show_station_range(pixels: &mut [u8], x: f64, y: f64) {
+    for py in (y as i32 - GRID.station_range)..(y as i32 + GRID.station_range) {
+        if py < 0 || py as usize >= GRID.height {
+            continue;
+        }
+
+        let y_index = (GRID.height - 1 - py as usize) * GRID.width * 4;
+
+        for px in (x as i32 - GRID.station_range)..(x as i32 + GRID.station_range) {
+            if px < 0 || px as usize >= GRID.width {
+                continue;
             }
+
+            let index = y_index + px as usize * 4;
+
+            pixels[index + 3] = 255;
+        }
+    }
+}
+
+/// Update the station list and the humidity image of the observation.
+fn update_weather(observations: &[Observation], observed_time: i64) {
+    let img_id = observed_time as u64;
+
+    let humidity_img = match make_humidity_img(observations, observed_time) {
+        Ok(img) => Some(img),
+        Err(err) => {
+            warn!("Fail to make humidity image: {}", err);
+            None
+        },
+    };
+
+    if let Some(img) = humidity_img.as_ref() {
+        let mut map = HUMIDITY_IMG.write().unwrap();
+
+        let current_secs = Utc::now().timestamp() as u64;
+        map.retain(|&time, _| current_secs < time + IMG_MAX_AGE);
+
+        map.insert(img_id, img.clone());
+    }
+
+    let latest = observations.iter()
+        .map(|obs| (obs.stn_id.as_str(), obs))
+        .collect::<HashMap<_, _>>();
+
+    let part_stations = STATION_INFO.iter()
+        .map(|(id, stn)| {
+            let obs = latest.get(id.as_str());
+
+            json!({
+                "id": id,
+                "name": stn.name,
+                "latitude": stn.latitude,
+                "longitude": stn.longitude,
+                "wind_dir": obs.and_then(|o| o.wind_dir),
+                "wind_speed": obs.and_then(|o| o.wind_speed),
+                "temperature": obs.and_then(|o| o.temperature),
+                "humidity": obs.and_then(|o| o.humidity),
+                "precipitation": obs.and_then(|o| o.precipitation),
+            })
         })
+        .collect::<Vec<_>>();
+
+    let json = json!({
+        "error": false,
+        "time": observed_time,
+        "humidity_id": humidity_img.map(|_| img_id),
+        "width": GRID.width,
+        "height": GRID.height,
+        "resolution": GRID.resolution,
+        "offset_x": GRID.x_offset,
+        "offset_y": GRID.y_offset,
+        "stations": part_stations,
+        "size": part_stations.len(),
+    }).to_string();
+
+    event_sys::update_cache(&WEATHER_STATIONS, json, Layer::Weather);
 }
 
-fn get_stations() -> Result<Vec<StationData>, String> {
-    reqwest::get("http://www.weather.go.kr/cgi-bin/aws/nph-aws_txt_min")
-        .and_then(|mut res| res.text())
-        .map_err(|err| err.to_string())
-        .and_then(|html| {
-            let begin_res = html.find("<table")
-                .and_then(|idx| html[idx..].find("javascript").map(|offset| idx + offset))
-                .and_then(|idx| html[..idx].rfind("<tr"));
-            let end_res = begin_res.as_ref()
-                .and_then(|&begin| html[begin..].find("</table").map(|offset| begin + offset));
+/// Humidity is a scalar, so it goes through the wind field as the x component.
+fn make_humidity_img(observations: &[Observation], observed_time: i64) -> Result<Vec<u8>, String> {
+    let mut pixels = vec![0; GRID.height * GRID.width * 4];
+    let mut station_data = Vec::new();
 
-            if let (Some(begin), Some(end)) = (begin_res, end_res) {
-                Ok((html, begin, end))
-            }
-            else {
-                Err("Fail to parse table".into())
-            }
+    for obs in observations {
+        if let (Some(station), Some(humidity)) = (STATION_INFO.get(&obs.stn_id), obs.humidity) {
+            let (x, y) = to_grid_point(station.latitude, station.longitude);
+            station_data.push((x, y, Vector2::new(humidity, 0.0)));
+
+            show_station_range(&mut pixels, x, y);
+        }
+    }
+
+    if station_data.is_empty() {
+        return Err("No humidity stations".into());
+    }
+
+    let field = Arc::new(WindField::new(station_data, observed_time, INTERPOLATION.clone()));
+    let grid = wind_field::interpolate_grid(&field, GRID.width, GRID.height, INTERPOLATION_WORKERS);
+
+    for y in 0..GRID.height {
+        let mut index = (GRID.height - 1 - y) * GRID.width * 4;
+
+        for x in 0..GRID.width {
+            let humidity = grid[y * GRID.width + x].x;
+
+            pixels[index] = 0_f64.max((255.0 * humidity / 100.0).round().min(255.0)) as u8;
+
+            index += 4;
+        }
+    }
+
+    encode_png(&pixels, GRID.width, GRID.height)
+}
+
+fn save_wind_observations(stations: &[StationData], observed_time: i64) {
+    let time = UNIX_EPOCH + Duration::new(observed_time as u64, 0);
+
+    let observations = stations.iter()
+        .map(|stn| db::models::NewWindObservation {
+            stn_id: stn.id.clone(),
+            latitude: stn.latitude,
+            longitude: stn.longitude,
+            wind_x: stn.wind.x,
+            wind_y: stn.wind.y,
+            observed_time: time,
         })
-        .and_then(|(html, mut begin, end)| {
-            let mut table: Vec<Vec<_>> = Vec::new();
+        .collect::<Vec<_>>();
 
-            while begin < end {
-                let mut row = Vec::new();
+    let result = db::insert_wind_observations(&observations)
+        .and_then(|_| db::delete_wind_observations_before(time - Duration::new(WIND_HISTORY_DURATION, 0)));
 
-                let end_tr = html[begin..].find("</tr").map(|offset| begin + offset);
-                if end_tr.is_none() {
-                    return Err("Fail to parse station data".into());
-                }
-                let end_tr = end_tr.unwrap();
-
-                loop {
-                    let begin_res = html[begin..].find("<td").map(|offset| begin + offset);
-                    if begin_res.is_none() {
-                        break;
-                    }
-                    begin = begin_res.unwrap();
-
-                    if begin > end_tr {
-                        break;
-                    }
-
-                    let begin_res = html[begin..].find('>').map(|offset| begin + offset);
-                    if begin_res.is_none() {
-                        break;
-                    }
-                    begin = begin_res.unwrap();
-
-                    let end_td_res = html[begin..].find("</td").map(|offset| begin + offset);
-                    if end_td_res.is_none() {
-                        break;
-                    }
-                    let end_td = end_td_res.unwrap();
-
-                    row.push(util::extract_text_from_html(&html[(begin + 1)..end_td]));
-                }
+    if let Err(err) = result {
+        warn!("Fail to save wind observations: {}", err);
+    }
+}
 
-                if row.len() > 16 {
-                    table.push(row);
-                }
+fn get_history_frame(time: u64) -> Result<Arc<HistoryFrame>, Custom<String>> {
+    let observed_time = UNIX_EPOCH.checked_add(Duration::new(time, 0))
+        .ok_or(Custom(Status::BadRequest, "Invalid time".into()))?;
+    let time = time as i64;
 
-                let begin_res = html[end_tr..].find("<tr").map(|offset| end_tr + offset);
-                if begin_res.is_none() {
-                    break;
-                }
-                begin = begin_res.unwrap();
-            }
+    if let Some(frame) = WIND_HISTORY.read().unwrap().get(&time) {
+        return Ok(frame.clone());
+    }
 
-            Ok(table)
+    let observations = db::get_wind_observations_at(observed_time)
+        .map_err(|err| Custom(Status::BadRequest, err.to_string()))?;
+
+    if observations.is_empty() {
+        return Err(Custom(Status::NotFound, "There is no wind history at that time".into()));
+    }
+
+    let stations = observations.into_iter()
+        .map(|obs| StationData {
+            id: obs.stn_id,
+            latitude: obs.latitude,
+            longitude: obs.longitude,
+            wind: Vector2::new(obs.wind_x, obs.wind_y),
         })
-        .map(|table| {
-            let mut data = Vec::new();
-
-            for row in table {
-                if let Some(station) = STATION_INFO.get(&row[0]) {
-                    let wind_dir = row[14].parse::<f64>();
-                    let wind_vel = row[16].parse::<f64>();
-
-                    if let (Ok(dir), Ok(vel)) = (wind_dir, wind_vel) {
-                        let angle = dir.to_radians();
-                        let dir_x = angle.sin() * vel;
-                        let dir_y = angle.cos() * vel;
-
-                        data.push(StationData {
-                            latitude: station.latitude,
-                            longitude: station.longitude,
-                            wind: Vector2::new(dir_x, dir_y),
-                        });
-                    }
-                }
+        .collect();
+
+    let wind_map = make_wind_map(stations, time)
+        .map_err(|err| Custom(Status::InternalServerError, err))?;
+    let frame = Arc::new(HistoryFrame {
+        metadata: wind_map.metadata,
+        img: wind_map.img,
+    });
+
+    {
+        let mut cache = WIND_HISTORY.write().unwrap();
+
+        if cache.len() >= MAX_HISTORY_CACHE_SIZE {
+            cache.clear();
+        }
+
+        cache.insert(time, frame.clone());
+    }
+
+    Ok(frame)
+}
+
+fn encode_png(pixels: &[u8], width: usize, height: usize) -> Result<Vec<u8>, String> {
+    let img_data = ByteVec::new();
+
+    let mut encoder = png::Encoder::new(img_data.clone(), width as u32, height as u32);
+    encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+    let img_result = encoder.write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels));
+
+    if let Err(err) = img_result {
+        return Err(err.to_string());
+    }
+
+    img_data.bytes().map_err(|_| "Fail to get image bytes".into())
+}
+
+/// Whether the web mercator tile overlaps the wind grid.
+fn is_tile_in_grid(z: u32, tile_x: u32, tile_y: u32) -> bool {
+    let tile_size = util::LL_RANGE / (1u64 << z) as f64;
+    let half_range = util::LL_RANGE / 2.0;
+
+    let min_x = tile_x as f64 * tile_size - half_range;
+    let max_y = half_range - tile_y as f64 * tile_size;
+    let (max_x, min_y) = (min_x + tile_size, max_y - tile_size);
+
+    min_x < GRID.x_offset + GRID.width as f64 * GRID.resolution && max_x > GRID.x_offset
+        && min_y < GRID.y_offset + GRID.height as f64 * GRID.resolution && max_y > GRID.y_offset
+}
+
+/// Render a tile by interpolating at each tile pixel, so it gets finer than the grid when zoomed in.
+fn make_wind_tile(frame: &WindFrame, z: u32, tile_x: u32, tile_y: u32) -> Result<Vec<u8>, String> {
+    let pixel_size = util::LL_RANGE / (TILE_SIZE as f64 * (1u64 << z) as f64);
+    let half_range = util::LL_RANGE / 2.0;
+    let term = frame.max - frame.min;
+
+    let mut pixels = vec![0; TILE_SIZE * TILE_SIZE * 4];
+
+    for py in 0..TILE_SIZE {
+        let my = half_range - ((tile_y as usize * TILE_SIZE + py) as f64 + 0.5) * pixel_size;
+        let gy = (my - GRID.y_offset) / GRID.resolution;
+
+        if gy < 0.0 || gy >= GRID.height as f64 {
+            continue;
+        }
+
+        for px in 0..TILE_SIZE {
+            let mx = ((tile_x as usize * TILE_SIZE + px) as f64 + 0.5) * pixel_size - half_range;
+            let gx = (mx - GRID.x_offset) / GRID.resolution;
+
+            if gx < 0.0 || gx >= GRID.width as f64 {
+                continue;
             }
 
-            data
-        })
+            if !frame.coverage[gy as usize * GRID.width + gx as usize] {
+                continue;
+            }
+
+            let wind = frame.field.interpolate(Point2::new(gx, gy));
+            let norm_wind_x = 255.0 * (wind.x - frame.min.x) / term.x;
+            let norm_wind_y = 255.0 * (wind.y - frame.min.y) / term.y;
+
+            // RGBA
+            let index = (py * TILE_SIZE + px) * 4;
+            pixels[index + 0] = 0_f64.max(norm_wind_x.floor().min(255.0)) as u8;
+            pixels[index + 1] = 0_f64.max(norm_wind_y.floor().min(255.0)) as u8;
+            pixels[index + 3] = 255;
+        }
+    }
+
+    encode_png(&pixels, TILE_SIZE, TILE_SIZE)
+}
+
+fn make_wind_stations(observations: &[Observation]) -> Vec<StationData> {
+    let mut data = Vec::new();
+
+    for obs in observations {
+        if let Some(station) = STATION_INFO.get(&obs.stn_id) {
+            if let (Some(dir), Some(vel)) = (obs.wind_dir, obs.wind_speed) {
+                let angle = dir.to_radians();
+                let dir_x = angle.sin() * vel;
+                let dir_y = angle.cos() * vel;
+
+                data.push(StationData {
+                    id: obs.stn_id.clone(),
+                    latitude: station.latitude,
+                    longitude: station.longitude,
+                    wind: Vector2::new(dir_x, dir_y),
+                });
+            }
+        }
+    }
+
+    data
 }